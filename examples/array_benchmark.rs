@@ -0,0 +1,60 @@
+//! Measures how long building small arrays in a loop takes. An array literal builds the array in one go, while the naive
+//! way appends one element at a time, by concatenating one-element arrays.
+
+use std::time::{Duration, Instant};
+
+use rslox::{interpret, vm::VM};
+
+const LITERAL: &str = "
+var result;
+{
+    var i = 0;
+    var array;
+    while (i < 500000) {
+        array = [i, i, i, i, i, i, i, i];
+        i = i + 1;
+    }
+    result = array;
+}
+result
+";
+
+const APPEND: &str = "
+var result;
+{
+    var i = 0;
+    var array;
+    while (i < 500000) {
+        array = [];
+        array = array + [i];
+        array = array + [i];
+        array = array + [i];
+        array = array + [i];
+        array = array + [i];
+        array = array + [i];
+        array = array + [i];
+        array = array + [i];
+        i = i + 1;
+    }
+    result = array;
+}
+result
+";
+
+fn measure(source: &str) -> Duration {
+    let mut vm = VM::new();
+    let start = Instant::now();
+    let result = interpret(source, &mut vm);
+    let elapsed = start.elapsed();
+
+    if let Err(e) = result {
+        eprintln!("{e}");
+    }
+    vm.reset_vm();
+    elapsed
+}
+
+fn main() {
+    println!("Array literal: {:?}", measure(LITERAL));
+    println!("Appending elements: {:?}", measure(APPEND));
+}
@@ -0,0 +1,42 @@
+//! Writes bytecode for `-((1.2 + 3.4) / 5.6)` directly to a chunk, without the compiler, and runs it on the VM.
+//! Operands are pushed before the instruction using them, so order of instructions matters.
+
+use rslox::{
+    chunk::{Chunk, OpCode},
+    value::Value,
+    vm::{VM, errors::VMError},
+};
+
+fn main() -> Result<(), VMError> {
+    let mut chunk = Chunk::new();
+    let line = 123;
+
+    write_constant(&mut chunk, 1.2, line);
+    write_constant(&mut chunk, 3.4, line);
+    chunk.write_chunk(OpCode::OpAdd as u8, line);
+
+    write_constant(&mut chunk, 5.6, line);
+    chunk.write_chunk(OpCode::OpDivide as u8, line);
+    chunk.write_chunk(OpCode::OpNegate as u8, line);
+    chunk.write_chunk(OpCode::OpReturn as u8, line);
+
+    let mut vm = VM::new();
+    let result = vm.run_chunk(chunk);
+    vm.reset_vm();
+
+    match result {
+        Ok(value) => println!("Result: {value}"),
+        Err(VMError::RuntimeError(message)) => eprintln!("{message}"),
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+/// Adds a number to constant pool, and writes instruction to push it onto the stack
+fn write_constant(chunk: &mut Chunk, number: f64, line: i32) {
+    let index = chunk
+        .add_constant(Value::from(number))
+        .expect("A few constants fit in the pool");
+    chunk.write_chunk(OpCode::OpConstant as u8, line);
+    chunk.write_chunk(index, line);
+}
@@ -0,0 +1,49 @@
+//! Measures cost of call frames. Creates a lot of short-lived VMs, like an embedder evaluating small snippets would,
+//! and runs deep recursion on a single VM, which needs a lot of frames.
+
+use std::time::Instant;
+
+use rslox::{constants::FRAMES_MAX, interpret, vm::VM};
+
+const SNIPPET: &str = "1 + 2";
+
+// Recursion goes 60 frames deep, close to the limit, and is repeated many times
+const RECURSION: &str = "
+fun depth(n) {
+    if (n == 0) return 0;
+    return 1 + depth(n - 1);
+}
+var total = 0;
+for (var i = 0; i < 20000; i = i + 1) {
+    total = total + depth(60);
+}
+total
+";
+
+fn run(label: &str, make_vm: fn() -> VM) {
+    let start = Instant::now();
+    for _ in 0..100_000 {
+        let mut vm = make_vm();
+        let _ = interpret(SNIPPET, &mut vm);
+        vm.reset_vm();
+    }
+    let snippets = start.elapsed();
+
+    let mut vm = make_vm();
+    let start = Instant::now();
+    let result = interpret(RECURSION, &mut vm);
+    let recursion = start.elapsed();
+    vm.reset_vm();
+
+    match result {
+        Ok(_) => println!("{label}: 100000 snippets in {snippets:?}, recursion in {recursion:?}"),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+fn main() {
+    run("VM::new", VM::new);
+    run("VM::with_frames_capacity", || {
+        VM::with_frames_capacity(FRAMES_MAX)
+    });
+}
@@ -0,0 +1,34 @@
+//! Measures how long reading a global variable in a tight loop takes. Loop counter and sum are locals, so reading the global
+//! `step` is the only global access in the loop body.
+
+use std::time::Instant;
+
+use rslox::{interpret, vm::VM};
+
+const SOURCE: &str = "
+var step = 3;
+var result;
+{
+    var i = 0;
+    var sum = 0;
+    while (i < 5000000) {
+        sum = sum + step;
+        i = i + 1;
+    }
+    result = sum;
+}
+result
+";
+
+fn main() {
+    let mut vm = VM::new();
+    let start = Instant::now();
+    let result = interpret(SOURCE, &mut vm);
+    let elapsed = start.elapsed();
+
+    match result {
+        Ok(sum) => println!("Computed {sum} in {elapsed:?}"),
+        Err(e) => eprintln!("{e}"),
+    }
+    vm.reset_vm();
+}
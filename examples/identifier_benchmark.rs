@@ -0,0 +1,45 @@
+//! Measures how long compiling identifier-heavy code takes. Resolving a local compares its name with every local in scope,
+//! so this mostly exercises identifier comparison.
+
+use std::{fmt::Write, time::Instant};
+
+use rslox::compiler::{CompilationContext, CompilerState, types::FunctionType};
+
+const LOCALS: usize = 200;
+const STATEMENTS: usize = 500;
+const ITERATIONS: usize = 20;
+
+fn main() {
+    // A block with many locals, and many statements reading them
+    let mut source = String::from("{\n");
+    for i in 0..LOCALS {
+        let _ = writeln!(source, "var variable_number_{i} = {i};");
+    }
+    for i in 0..STATEMENTS {
+        let _ = writeln!(
+            source,
+            "variable_number_{} = variable_number_{} + variable_number_{};",
+            i % LOCALS,
+            (i * 7) % LOCALS,
+            (i * 13) % LOCALS
+        );
+    }
+    source.push_str("}\n");
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let mut context = CompilationContext::new(&source);
+        context.push(CompilerState::new(FunctionType::default_script()));
+        if let Err(e) = context.compile() {
+            eprintln!("Compiler Error: {e}");
+            return;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "Compiled {} lines {ITERATIONS} times in {elapsed:?}, {:?} per compilation",
+        LOCALS + STATEMENTS + 2,
+        elapsed / ITERATIONS as u32
+    );
+}
@@ -0,0 +1,34 @@
+//! Measures how long running a tight `while` loop takes. Most of the work is checking the loop condition,
+//! so this mostly exercises conditional jumps.
+
+use std::time::Instant;
+
+use rslox::{interpret, vm::VM};
+
+// Locals are used, because globals are looked up by name, which would take most of the time
+const SOURCE: &str = "
+var result;
+{
+    var i = 0;
+    var sum = 0;
+    while (i < 5000000) {
+        sum = sum + i;
+        i = i + 1;
+    }
+    result = sum;
+}
+result
+";
+
+fn main() {
+    let mut vm = VM::new();
+    let start = Instant::now();
+    let result = interpret(SOURCE, &mut vm);
+    let elapsed = start.elapsed();
+
+    match result {
+        Ok(sum) => println!("Computed {sum} in {elapsed:?}"),
+        Err(e) => eprintln!("{e}"),
+    }
+    vm.reset_vm();
+}
@@ -1,11 +1,18 @@
 /// This module handles operation codes for the vm
 /// It's the byte representation of code for VM to execute
-use crate::value::Value;
+use std::collections::HashMap;
+
+use crate::value::{Literal, Value};
+
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug)]
-/// Error thrown when invalid opcode gets detected.
+/// Error thrown when invalid opcode gets detected, or when a chunk can't hold more constants.
 pub enum ChunkError {
     InvalidOpCode(u8),
+    /// Constant index is a single byte operand, so a chunk can only have 256 constants
+    TooManyConstants,
 }
 
 /// #[repr(u8)] tells that each `OpCode`'s variant should take only one byte, consistently across all platforms.
@@ -74,6 +81,45 @@ pub enum OpCode {
     OpJump = 22,
     OpLoop = 23,
     OpCall = 24,
+    /// Pops the number of values from the stack, stores them in an array, and pushes the array onto the stack.
+    /// Takes 2 bytes: 1 for `OpCode`, 1 for number of values.
+    OpBuildArray = 25,
+    /// Pops an array from the stack and pushes its elements, first element first. Array should have exact number of elements.
+    /// Takes 2 bytes: 1 for `OpCode`, 1 for number of elements expected.
+    OpUnpack = 26,
+    /// Pushes a copy of the value at the top of the stack, so it can be used again without evaluating the expression twice.
+    /// Takes a single byte.
+    OpDup = 27,
+    /// Pops given number of values from the stack, e.g. locals at the end of a scope.
+    /// Takes 2 bytes: 1 for `OpCode`, 1 for number of values.
+    OpPopN = 28,
+    /// Pops two values from the stack, checks if left value is greater than or equal to right value, and pushes the result back onto the stack.
+    /// Not same as negating `OpLess`, which gives wrong result for `NaN`. Takes a single byte.
+    OpGreaterEqual = 29,
+    /// Pops two values from the stack, checks if left value is less than or equal to right value, and pushes the result back onto the stack.
+    /// Not same as negating `OpGreater`, which gives wrong result for `NaN`. Takes a single byte.
+    OpLessEqual = 30,
+    /// Pops a value from the stack and pushes its built-in property, like `length` of a string. Name of the property is stored in the constant pool.
+    /// Takes 2 bytes: 1 for `OpCode`, 1 for constant index of the name.
+    OpGetProperty = 31,
+    /// Returns `nil` from the current function, same as `OpNil` followed by `OpReturn`. Used for implicit returns at the end of functions.
+    /// Takes a single byte.
+    OpReturnNil = 32,
+    /// Pops the condition from the stack, and jumps forward if it's falsey. Used when condition isn't needed after the jump, like in `if` and loops.
+    /// Takes 3 bytes: 1 for `OpCode`, 2 for distance of the jump.
+    OpPopJumpIfFalse = 33,
+    /// Jumps forward if the value at the top of the stack isn't `nil`. Value is kept on the stack either way.
+    /// Takes 3 bytes: 1 for `OpCode`, 2 for jump offset.
+    OpJumpIfNotNil = 34,
+    /// Copies the value at the top of the stack below the value under it, so `[a, b]` becomes `[b, a, b]`. Used by chained
+    /// comparisons, like `a < b < c`, to keep `b` for the next comparison. Takes a single byte.
+    OpTuck = 35,
+    /// Checks that argument in a parameter's slot has the type the parameter is annotated with, like `fun f(x: number)`.
+    /// Emitted at the start of the function body. Takes 3 bytes: 1 for `OpCode`, 1 for the slot, 1 for constant index of the type name.
+    OpCheckType = 36,
+    /// Pops the index and the array, and pushes the element at that index, like `a[i]`. Negative index counts from the end
+    /// of the array, so `a[-1]` is the last element. Takes a single byte.
+    OpGetIndex = 37,
 }
 
 /// We need to convert `u8` to `OpCode`. Implementing `TryFrom` makes sense because `u8` can
@@ -107,6 +153,19 @@ impl TryFrom<u8> for OpCode {
             22 => Ok(Self::OpJump),
             23 => Ok(Self::OpLoop),
             24 => Ok(Self::OpCall),
+            25 => Ok(Self::OpBuildArray),
+            26 => Ok(Self::OpUnpack),
+            27 => Ok(Self::OpDup),
+            28 => Ok(Self::OpPopN),
+            29 => Ok(Self::OpGreaterEqual),
+            30 => Ok(Self::OpLessEqual),
+            31 => Ok(Self::OpGetProperty),
+            32 => Ok(Self::OpReturnNil),
+            33 => Ok(Self::OpPopJumpIfFalse),
+            34 => Ok(Self::OpJumpIfNotNil),
+            35 => Ok(Self::OpTuck),
+            36 => Ok(Self::OpCheckType),
+            37 => Ok(Self::OpGetIndex),
             _ => Err(ChunkError::InvalidOpCode(value)),
         }
     }
@@ -122,6 +181,30 @@ pub struct Chunk {
     pub constants: Vec<Value>,
     /// line number of code byte being written
     pub lines: Vec<i32>,
+    /// Indexes of literals already in the constant pool, so adding an equal literal again reuses its slot
+    constant_indexes: HashMap<ConstantKey, u8>,
+}
+
+/// Key to look up a literal constant. Numbers are compared by bits, so `0` and `-0` get separate slots.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Nil,
+    Bool(bool),
+    Number(u64),
+    String(String),
+}
+
+impl ConstantKey {
+    /// Returns `None` for values which shouldn't be shared, like functions
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Literal(Literal::Nil) => Some(Self::Nil),
+            Value::Literal(Literal::Bool(boolean)) => Some(Self::Bool(*boolean)),
+            Value::Literal(Literal::Number(number)) => Some(Self::Number(number.to_bits())),
+            Value::Literal(Literal::String(string)) => Some(Self::String(string.clone())),
+            Value::Obj(_) => None,
+        }
+    }
 }
 
 impl Default for Chunk {
@@ -139,6 +222,7 @@ impl Chunk {
             code: vec![],
             constants: vec![],
             lines: vec![],
+            constant_indexes: HashMap::new(),
         }
     }
 
@@ -148,9 +232,24 @@ impl Chunk {
         self.lines.push(line);
     }
 
-    /// Adds constant to constant pool and returns the index of constant in the pool
-    pub fn add_constant(&mut self, value: Value) -> usize {
+    /// Adds constant to constant pool and returns the index of constant in the pool.
+    /// If an equal literal is already in the pool, its index is returned instead of adding it again. Objects, like functions, are always added.
+    /// # Errors
+    ///
+    /// Returns `ChunkError::TooManyConstants` if pool already has 256 constants, because index wouldn't fit in a single byte operand
+    pub fn add_constant(&mut self, value: Value) -> Result<u8, ChunkError> {
+        let key = ConstantKey::from_value(&value);
+        if let Some(key) = &key
+            && let Some(&index) = self.constant_indexes.get(key)
+        {
+            return Ok(index);
+        }
+
+        let index = u8::try_from(self.constants.len()).map_err(|_| ChunkError::TooManyConstants)?;
         self.constants.push(value);
-        self.constants.len() - 1
+        if let Some(key) = key {
+            self.constant_indexes.insert(key, index);
+        }
+        Ok(index)
     }
 }
@@ -0,0 +1,59 @@
+use crate::{
+    chunk::{Chunk, ChunkError},
+    value::{Value, objects::FunctionObject},
+};
+
+#[test]
+fn equal_literals_share_constant_index() {
+    let mut chunk = Chunk::new();
+    let number = chunk.add_constant(Value::from(1.0)).unwrap();
+    let string = chunk.add_constant(Value::from("one".to_owned())).unwrap();
+    assert_eq!(chunk.add_constant(Value::from(1.0)).unwrap(), number);
+    assert_eq!(
+        chunk.add_constant(Value::from("one".to_owned())).unwrap(),
+        string
+    );
+    assert_eq!(
+        chunk.add_constant(Value::from(true)).unwrap(),
+        chunk.add_constant(Value::from(true)).unwrap()
+    );
+    assert_eq!(
+        chunk.add_constant(Value::new_nil()).unwrap(),
+        chunk.add_constant(Value::new_nil()).unwrap()
+    );
+    assert_eq!(chunk.constants.len(), 4);
+
+    // `0` and `-0` are equal, but print differently, so they're kept apart
+    let zero = chunk.add_constant(Value::from(0.0)).unwrap();
+    assert_ne!(chunk.add_constant(Value::from(-0.0)).unwrap(), zero);
+    // Different types with similar looking values aren't shared
+    assert_ne!(
+        chunk.add_constant(Value::from("1".to_owned())).unwrap(),
+        number
+    );
+}
+
+#[test]
+fn functions_are_never_shared() {
+    let mut chunk = Chunk::new();
+    let first = chunk.add_constant(FunctionObject::new().into()).unwrap();
+    let second = chunk.add_constant(FunctionObject::new().into()).unwrap();
+    assert_ne!(first, second);
+}
+
+#[test]
+fn constant_pool_is_capped_at_256() {
+    let mut chunk = Chunk::new();
+    for i in 0..256 {
+        assert_eq!(
+            chunk.add_constant(Value::from(f64::from(i))).unwrap(),
+            u8::try_from(i).unwrap()
+        );
+    }
+    assert!(matches!(
+        chunk.add_constant(Value::from(256.0)),
+        Err(ChunkError::TooManyConstants)
+    ));
+    // Existing constants can still be reused from a full pool
+    assert_eq!(chunk.add_constant(Value::from(255.0)).unwrap(), 255);
+}
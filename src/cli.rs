@@ -1,9 +1,9 @@
 /// This module handles CLI arguments and takes actions. Simplified using `clap` crate
-use std::io::{self, Write};
+use std::io::{self, BufRead, Write};
 
 use crate::{
-    compiler::{CompilationContext, CompilerState, types::FunctionType},
-    vm::VM,
+    compiler::CompilationContext,
+    vm::{VM, errors::VMError},
 };
 use clap::Parser;
 
@@ -12,38 +12,74 @@ use clap::Parser;
 pub struct Cli {
     #[arg(short, long, value_name = "FILE")]
     pub file: Option<String>,
+    /// Print tokens scanned from the file, without executing it
+    #[arg(long, value_name = "FILE")]
+    pub dump_tokens: Option<String>,
+    /// Print bytecode of every function in the file, without executing it
+    #[arg(long, value_name = "FILE")]
+    pub disassemble: Option<String>,
+    /// Print warnings about suspicious code, like expression statements having no effect
+    #[arg(short, long)]
+    pub warnings: bool,
+    /// Don't allow redefining global variables and functions in REPL
+    #[arg(long)]
+    pub strict: bool,
+    /// Floor result of '/' when both operands are whole numbers, so `7 / 2` is `3`
+    #[arg(long)]
+    pub integer_division: bool,
 }
 
-/// Starts a repl and execute code
-/// # Panics
+/// Starts a repl and execute code. In strict mode, redefining a global is a runtime error. With integer division, `/` floors result of whole numbers.
+/// # Errors
 ///
-/// This function should not Panic on `unwrap` because error is already checked.
-pub fn repl() {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+/// Returns `VMError::Exit` if a line called `exit()`, see `run_repl`
+pub fn repl(cli: &Cli) -> Result<(), VMError> {
+    let mut vm = VM::new();
+    vm.strict_globals = cli.strict;
+    vm.integer_division = cli.integer_division;
+    run_repl(cli, &mut io::stdin().lock(), &mut io::stdout(), &mut vm)
+}
+
+/// Runs a REPL session, reading lines from `input` until end of input (Ctrl-D) or `exit`. Prompts and results are written to `output`.
+///
+/// `vm` is reset before returning, however the session ends, so objects created by the last line aren't leaked.
+/// Process is never exited from here, it's up to the caller, like `main`, to pick an exit code.
+/// # Errors
+///
+/// Returns `VMError::Exit` if a line called `exit()`, even with code 0. Other errors are written to `output`, and the session goes on.
+pub fn run_repl(
+    cli: &Cli,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    vm: &mut VM,
+) -> Result<(), VMError> {
     let mut line = String::new();
+    // Lines read before the current one, so errors point at the line of the whole session
+    let mut lines_read = 0;
 
-    let mut vm = VM::new();
+    // A single context is used for whole session, and extended with every new line
+    let mut context = CompilationContext::new("");
+    context.set_warnings(cli.warnings);
 
     loop {
-        print!("> ");
-
         // Display `>` on the screen.
-        if let Err(e) = stdout.flush() {
+        if let Err(e) = write!(output, "> ").and_then(|()| output.flush()) {
             eprintln!("Error flushing stdout: {e}");
             break;
         }
 
         // Read complete line. If it's successful, update the line variable
-        match stdin.read_line(&mut line) {
+        match input.read_line(&mut line) {
             // reading line is successful
             Ok(bytes_read) => {
                 // total bytes read shouldn't be '0'. Exit if value is '0'
                 if bytes_read == 0 {
-                    println!();
+                    let _ = writeln!(output);
                     break;
                 }
 
+                lines_read += 1;
+
                 // remove all whitespaces from the end
                 let source = line.trim_end();
                 // if input is empty after removing spaces, there's no need to execute anything
@@ -58,34 +94,42 @@ pub fn repl() {
                     break;
                 }
 
-                let mut context = CompilationContext::new(&line);
-                let function_type = FunctionType::default_script();
-                context.push(CompilerState::new(function_type));
+                // Context owns the line while it's compiled, and frees it when the next one is passed
+                context.extend_owned(source.to_owned(), lines_read);
 
                 let top_function = context.compile();
-
-                if let Err(e) = top_function {
-                    println!("{e}");
-                    line.clear();
-                    continue;
+                for warning in context.take_warnings() {
+                    eprintln!("{warning}");
                 }
 
-                let top_function = top_function.unwrap();
-                // Value on stack should be garbage collected
-                let stack_value = top_function.clone();
-                vm.replace_or_push(stack_value, 0);
+                let top_function = match top_function {
+                    Ok(top_function) => top_function,
+                    Err(e) => {
+                        let _ = writeln!(output, "{e}");
+                        line.clear();
+                        continue;
+                    }
+                };
 
-                let call_result = vm.call(top_function, 0);
-                if let Err(e) = call_result {
-                    println!("{e}");
-                    line.clear();
-                    continue;
-                }
-                let interpret_result = vm.interpret();
-                if let Err(e) = interpret_result {
-                    println!("{e}");
+                // Globals, and objects they reference, are kept for next lines
+                // Line ending with an expression without ';' produces a result, show it like other REPLs do
+                let result = vm.run_isolated(top_function.clone());
+                // Script of the line won't be run again, functions declared in it are kept for next lines
+                vm.free_script(top_function);
+                match result {
+                    Ok(result) if !result.is_nil() => {
+                        let _ = writeln!(output, "{}", result.debug_repr());
+                    }
+                    Ok(_) => {}
+                    // Script asked to quit, clean up and let the caller exit
+                    Err(error @ VMError::Exit(_)) => {
+                        vm.reset_vm();
+                        return Err(error);
+                    }
+                    Err(e) => {
+                        let _ = writeln!(output, "{e}");
+                    }
                 }
-                vm.reset_vm();
             }
             // Display error if reading line from cli is unsuccessful
             Err(e) => {
@@ -96,4 +140,8 @@ pub fn repl() {
         // clear/empty the line for new input.
         line.clear();
     }
+
+    // Every way out of the loop, end of input, `exit` or an IO error, ends up here
+    vm.reset_vm();
+    Ok(())
 }
@@ -76,11 +76,11 @@ impl CompilationContext<'_> {
 
     /// Adds constant to constant pool and returns its index
     pub(super) fn make_constant(&mut self, value: Value) -> Result<u8, CompilerError> {
-        let constant = self.compiler_mut().chunk_mut().add_constant(value);
-        // Only allows 256 constants to be stored in constant pool
-        let constant = u8::try_from(constant)
-            .map_err(|_| self.construct_token_error(false, "Too many constants in one chunk"))?;
-        Ok(constant)
+        // Same literal, or name of a global, used again gets its existing slot
+        self.compiler_mut()
+            .chunk_mut()
+            .add_constant(value)
+            .map_err(|_| self.construct_token_error(false, "Too many constants in one chunk"))
     }
     /// Writes a byte to the `chunk`
     pub(super) fn emit_byte(&mut self, byte: u8) -> Result<(), CompilerError> {
@@ -91,10 +91,9 @@ impl CompilationContext<'_> {
         Ok(())
     }
 
-    /// Writes `OpReturn` instruction at the end of the bytecode
+    /// Writes instruction returning `nil`, at the end of the bytecode or for `return;`
     pub(super) fn emit_return(&mut self) -> Result<(), CompilerError> {
-        self.emit_byte(OpCode::OpNil as u8)?;
-        self.emit_byte(OpCode::OpReturn as u8)
+        self.emit_byte(OpCode::OpReturnNil as u8)
     }
 
     /// Simply writes 2 bytes in order
@@ -8,7 +8,11 @@ impl CompilationContext<'_> {
     /// Responsible to handle all top level statements and declarations
     pub(super) fn declaration(&mut self) -> Result<(), CompilerError> {
         if self.match_curr_ty(TokenType::Fun)? {
-            self.fun_declaration()?;
+            if self.is_hoisted_function() {
+                self.skip_function_declaration()?;
+            } else {
+                self.fun_declaration()?;
+            }
         }
         // If current token type is var, emit bytecode for variable declaration, otherwise proceed with other types of statements
         else if self.match_curr_ty(TokenType::Var)? {
@@ -22,29 +26,142 @@ impl CompilationContext<'_> {
         Ok(())
     }
 
+    /// Compiles only top-level function declarations of the source, skipping everything else. Two functions can't be
+    /// nested at top level, so tokens are skipped by counting brackets, and `fun` at the start of a statement starts a declaration.
+    pub(super) fn compile_hoisted_functions(&mut self) -> Result<(), CompilerError> {
+        self.parser.advance().map_err(CompilerError::ParserError)?;
+        // How deep current token is nested in parentheses, braces and square brackets
+        let mut depth = 0usize;
+        let mut statement_start = true;
+        while !self.check_current(TokenType::Eof) {
+            if depth == 0 && statement_start && self.match_curr_ty(TokenType::Fun)? {
+                if self.check_current(TokenType::Identifier) {
+                    // Safe to unwrap, `fun` has just been consumed
+                    let start = self.parser.previous.as_ref().unwrap().start;
+                    self.fun_declaration()?;
+                    self.hoisted_functions.push(start);
+                } else {
+                    statement_start = false;
+                }
+                continue;
+            }
+
+            let ty = self.get_current_token_ty()?;
+            match ty {
+                TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => depth += 1,
+                TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => {
+                    depth = depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+            statement_start =
+                depth == 0 && matches!(ty, TokenType::Semicolon | TokenType::RightBrace);
+            self.parser.advance().map_err(CompilerError::ParserError)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if `fun` keyword just consumed starts a top-level function declaration which has already been compiled
+    fn is_hoisted_function(&self) -> bool {
+        // Declarations of imported files and blocks are nested in statements
+        if self.stack.len() > 1 || self.statement_depth > 0 {
+            return false;
+        }
+        self.parser
+            .previous
+            .as_ref()
+            .is_some_and(|token| self.hoisted_functions.contains(&token.start))
+    }
+
+    /// Skips the rest of a function declaration, after `fun` keyword, up to the brace closing its body
+    fn skip_function_declaration(&mut self) -> Result<(), CompilerError> {
+        // Declaration has been compiled before, so body is known to be there and its braces are balanced
+        while !self.match_curr_ty(TokenType::LeftBrace)? {
+            self.parser.advance().map_err(CompilerError::ParserError)?;
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            if self.match_curr_ty(TokenType::LeftBrace)? {
+                depth += 1;
+            } else if self.match_curr_ty(TokenType::RightBrace)? {
+                depth -= 1;
+            } else {
+                self.parser.advance().map_err(CompilerError::ParserError)?;
+            }
+        }
+        Ok(())
+    }
+
     fn fun_declaration(&mut self) -> Result<(), CompilerError> {
         let global = self.parse_variable("Expected function name")?;
+        // Safe to unwrap, name has just been consumed
+        let name = self.parser.previous.as_ref().unwrap().as_str(self.source);
         self.mark_initialized();
-        self.compile_function()?;
+        self.compile_function(Some(name))?;
         self.define_variable(global)
     }
 
     /// Generates bytecode to declare a variable
     pub(super) fn var_declaration(&mut self) -> Result<(), CompilerError> {
-        // Get the index of variable name, stored in constant pool
-        let global = self.parse_variable("Expected variable name")?;
-        if self.match_curr_ty(TokenType::Equal)? {
-            // Current token is equal, evaluate the expression on the right hand side, which will be pushed on VM's stack
-            self.expression()?;
-        } else {
-            // No value has been assigned to the variable. Assign `Nil` by default, which will be pushed on VM's stack
-            self.emit_byte(OpCode::OpNil as u8)?;
+        // `var (a, b) = f();` declares multiple variables from values returned by a function
+        if self.match_curr_ty(TokenType::LeftParen)? {
+            return self.var_destructuring();
+        }
+
+        // `var a = 1, b, c = 3;` declares variables one by one, from left to right
+        loop {
+            // Get the index of variable name, stored in constant pool
+            let global = self.parse_variable("Expected variable name")?;
+            if self.match_curr_ty(TokenType::Equal)? {
+                // Current token is equal, evaluate the expression on the right hand side, which will be pushed on VM's stack
+                self.expression()?;
+            } else {
+                // No value has been assigned to the variable. Assign `Nil` by default, which will be pushed on VM's stack
+                self.emit_byte(OpCode::OpNil as u8)?;
+            }
+
+            // Define variable before next one is parsed, so next initializer can use it
+            self.define_variable(global)?;
+
+            if !self.match_curr_ty(TokenType::Comma)? {
+                break;
+            }
+        }
+        // All declarations have been parsed. Consume ';' from the end.
+        self.consume(TokenType::Semicolon, "Expected ';'")
+    }
+
+    /// Generates bytecode to declare variables from multiple values, like `var (quotient, remainder) = divide(7, 2);`
+    fn var_destructuring(&mut self) -> Result<(), CompilerError> {
+        // Indexes of variable names, stored in constant pool
+        let mut globals = vec![];
+        loop {
+            globals.push(self.parse_variable("Expected variable name")?);
+            if !self.match_curr_ty(TokenType::Comma)? {
+                break;
+            }
         }
-        // Variable declaration and initialization has been parsed. Consume ';' from the end.
+        self.consume(TokenType::RightParen, "Expected ')' after variable names")?;
+        self.consume(TokenType::Equal, "Expected '=' after variable names")?;
+        // Should produce the multiple values, as an array
+        self.expression()?;
         self.consume(TokenType::Semicolon, "Expected ';'")?;
 
-        // Define global variable
-        self.define_variable(global)?;
+        let count = u8::try_from(globals.len())
+            .map_err(|_| self.construct_token_error(false, "Too many variables to unpack"))?;
+        // Replaces the array by its elements on the stack
+        self.emit_bytes(OpCode::OpUnpack as u8, count)?;
+
+        if self.compiler().scope_depth > 0 {
+            // Elements are already in the stack slots of locals, in order.
+            self.mark_last_initialized(globals.len());
+            return Ok(());
+        }
+
+        // Last element is on the top of the stack, so it's defined first
+        for global in globals.into_iter().rev() {
+            self.define_variable(global)?;
+        }
 
         Ok(())
     }
@@ -1,4 +1,7 @@
-use crate::compiler::{CompilationContext, parser::ParserError};
+use crate::{
+    compiler::{CompilationContext, parser::ParserError},
+    scanner::token::Token,
+};
 
 /// Custom Errors for compiler
 #[derive(Debug)]
@@ -38,4 +41,15 @@ impl CompilationContext<'_> {
         };
         CompilerError::ParserError(error)
     }
+
+    /// Records a warning at the given token, if warnings are enabled. Formatted like errors, so it's easy to locate.
+    pub(super) fn warn(&mut self, token: &Token, message: &str) {
+        if self.warnings_enabled {
+            let lexeme = token.lexeme(self.source);
+            self.warnings.push(format!(
+                "[line {}:{}] Warning at '{lexeme}': {message}",
+                token.line, token.column
+            ));
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    chunk::OpCode,
     compiler::{
         CompilationContext,
         errors::CompilerError,
@@ -25,8 +26,60 @@ impl CompilationContext<'_> {
         Ok(())
     }
 
+    /// Compiles an array literal, like `[a, b, c]`. Initial '[' has already been consumed.
+    ///
+    /// Elements are left on the stack in order, and `OpBuildArray` moves all of them into the array at once.
+    pub(super) fn array(&mut self, _: bool) -> Result<(), CompilerError> {
+        let mut element_count = 0u8;
+
+        if !self.check_current(TokenType::RightBracket) {
+            loop {
+                self.expression()?;
+                element_count = element_count.checked_add(1).ok_or_else(|| {
+                    self.construct_token_error(
+                        false,
+                        "Can't have more than 255 elements in an array literal.",
+                    )
+                })?;
+
+                if !self.match_curr_ty(TokenType::Comma)? {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RightBracket,
+            "Expected ']' after array elements.",
+        )?;
+        self.emit_bytes(OpCode::OpBuildArray as u8, element_count)
+    }
+
+    /// Compiles property access. Receiver has already been compiled and '.' has been consumed.
+    pub(super) fn dot(&mut self, _: bool) -> Result<(), CompilerError> {
+        self.consume(TokenType::Identifier, "Expected property name after '.'.")?;
+        // Safe to unwrap, identifier has just been consumed
+        let name = self.parser.previous.clone().unwrap();
+        let constant = self.identifier_constant(&name)?;
+        self.emit_bytes(OpCode::OpGetProperty as u8, constant)
+    }
+
+    /// Compiles indexing, like `a[i]`. Array has already been compiled and '[' has been consumed.
+    pub(super) fn index(&mut self, _: bool) -> Result<(), CompilerError> {
+        self.expression()?;
+        self.consume(TokenType::RightBracket, "Expected ']' after index.")?;
+        self.emit_byte(OpCode::OpGetIndex as u8)
+    }
+
     /// Executes instructions according to precedence.
     pub(super) fn parse_precedence(&mut self, precedence: Precedence) -> Result<(), CompilerError> {
+        // Grouping and unary operators call this recursively
+        self.enter_nesting()?;
+        self.parse_operands(precedence)?;
+        self.exit_nesting();
+        Ok(())
+    }
+
+    fn parse_operands(&mut self, precedence: Precedence) -> Result<(), CompilerError> {
         // Parser already advanced one time, so this is second advance call
         // So in the case of `2+1`, parser would be at `+`
         self.parser.advance().map_err(CompilerError::ParserError)?;
@@ -53,13 +106,11 @@ impl CompilationContext<'_> {
                     // If operator has infix rule, execute it
                     infix_rule(self, can_assign)?;
                 }
+            }
 
-                // After the infix rule, like expression `a * b`, there shouldn't be any equal sign or `can_assign` should be false. This throws error when we right something like `a * b = c + d;`
-                if can_assign && self.match_curr_ty(TokenType::Equal)? {
-                    return Err(CompilerError::ExpressionError(
-                        "Invalid assignment target".to_owned(),
-                    ));
-                }
+            // Valid assignment consumes '=' in variable's prefix rule. If it's still there, like in `a * b = c + d;` or `1 = 2;`, left side can't be assigned to.
+            if can_assign && self.match_curr_ty(TokenType::Equal)? {
+                return Err(self.construct_token_error(false, "Invalid assignment target."));
             }
         } else {
             // Token should have an infix rule
@@ -1,23 +1,28 @@
 use crate::{
     chunk::OpCode,
-    compiler::{CompilationContext, errors::CompilerError, types::FunctionType},
+    compiler::{CompilationContext, TailCall, errors::CompilerError, types::FunctionType},
     scanner::token::TokenType,
     value::objects::FunctionObject,
 };
 
+/// Types a parameter can be annotated with. `nil` is left out, because a parameter which can only be `nil` isn't useful.
+const TYPE_NAMES: [&str; 5] = ["bool", "number", "string", "function", "array"];
+
 impl CompilationContext<'_> {
-    pub(super) fn compile_function(&mut self) -> Result<(), CompilerError> {
-        let mut fun_ty = FunctionType::default_function();
-        let mut fun_obj: FunctionObject = fun_ty.into();
-        // Safe to unwrap
-        fun_obj.name = Some(self.parser.previous.as_ref().unwrap().as_str(self.source));
-        fun_ty = fun_obj.into();
+    /// Compiles parameters and body of a function, and emits the function as a constant. Used by both function declarations
+    /// and function expressions, only declarations bind the function to a variable afterwards. Anonymous functions have no `name`.
+    pub(super) fn compile_function(&mut self, name: Option<String>) -> Result<(), CompilerError> {
+        let mut fun_obj: FunctionObject = FunctionType::default_function().into();
+        fun_obj.name = name;
+        let fun_ty = fun_obj.into();
 
         let child_compiler = super::CompilerState::new(fun_ty);
         self.push(child_compiler);
 
         self.begin_scope();
         self.consume(TokenType::LeftParen, "Expected '(' after function name")?;
+        // Slots of annotated parameters, and constant indexes of their type names
+        let mut annotations = vec![];
 
         if !self.check_current(TokenType::RightParen) {
             loop {
@@ -36,6 +41,13 @@ impl CompilationContext<'_> {
 
                 let constant = self.parse_variable("Expected parameter name")?;
                 self.define_variable(constant)?;
+                if self.match_curr_ty(TokenType::Colon)? {
+                    // Parameter has just been declared as the last local
+                    let slot = u8::try_from(self.compiler().locals.len() - 1).map_err(|_| {
+                        self.construct_token_error(false, "Too many local variables in scope")
+                    })?;
+                    annotations.push((slot, self.type_annotation()?));
+                }
 
                 if !self.match_curr_ty(TokenType::Comma)? {
                     break;
@@ -45,7 +57,15 @@ impl CompilationContext<'_> {
 
         self.consume(TokenType::RightParen, "Expected ')' after parameters")?;
         self.consume(TokenType::LeftBrace, "Expected '{' before function bofy")?;
+        // Arguments are checked before anything else in the body runs
+        for (slot, type_name) in annotations {
+            self.emit_bytes(OpCode::OpCheckType as u8, slot)?;
+            self.emit_byte(type_name)?;
+        }
+        // Function declarations don't go through `statement`, nested functions should be counted here
+        self.enter_nesting()?;
         self.block()?;
+        self.exit_nesting();
 
         let function_object = self.end_compiler()?;
 
@@ -53,6 +73,35 @@ impl CompilationContext<'_> {
         self.emit_bytes(OpCode::OpConstant as u8, constant)
     }
 
+    /// Compiles the type of a parameter, after ':'. Returns constant index of the type name, which should be one of the types
+    /// values can have.
+    fn type_annotation(&mut self) -> Result<u8, CompilerError> {
+        self.consume(TokenType::Identifier, "Expected type name after ':'.")?;
+        // Safe to unwrap, identifier has just been consumed
+        let token = self.parser.previous.clone().unwrap();
+        let type_name = token.lexeme(self.source);
+        if !TYPE_NAMES.contains(&type_name) {
+            let message = format!(
+                "Unknown type '{type_name}', expected one of: {}.",
+                TYPE_NAMES.join(", ")
+            );
+            return Err(self.construct_token_error(false, &message));
+        }
+        self.identifier_constant(&token)
+    }
+
+    /// Compiles a function expression, like `fun (a, b) { return a + b; }`, which evaluates to the function without declaring
+    /// a variable. Name is optional, like `fun add(a, b) {...}`, and only shows up when printing the function and in errors.
+    pub(super) fn function_expression(&mut self, _: bool) -> Result<(), CompilerError> {
+        let name = if self.match_curr_ty(TokenType::Identifier)? {
+            // Safe to unwrap, identifier has just been consumed
+            Some(self.parser.previous.as_ref().unwrap().as_str(self.source))
+        } else {
+            None
+        };
+        self.compile_function(name)
+    }
+
     pub(super) fn arguments_list(&mut self) -> Result<u8, CompilerError> {
         let mut arg_count = 0u8;
 
@@ -79,6 +128,27 @@ impl CompilationContext<'_> {
 
     pub(super) fn call(&mut self, _: bool) -> Result<(), CompilerError> {
         let arg_count = self.arguments_list()?;
+        self.has_side_effect = true;
+
+        if self.in_tail_position() {
+            let (FunctionType::Function(function) | FunctionType::Script(function)) =
+                &self.compiler().function_type;
+            let tail_call = TailCall {
+                function: function.name.clone(),
+                offset: self.compiler().chunk().code.len(),
+            };
+            self.tail_calls.push(tail_call);
+        }
         self.emit_bytes(OpCode::OpCall as u8, arg_count)
     }
+
+    /// Returns true if the call just compiled is the whole operand of a `return` statement, like `return f(x);`.
+    ///
+    /// Operators and grouping compile their operands one level deeper, so `return 1 + f(x);` or `return (f(x));` aren't in
+    /// tail position. `;` right after the call means its result isn't used by anything else, like in `return f(x)(y);`,
+    /// `return f(x) + 1;` or `return f(x), g(y);`.
+    pub(super) fn in_tail_position(&self) -> bool {
+        self.return_operand_depth == Some(self.nesting_depth)
+            && self.check_current(TokenType::Semicolon)
+    }
 }
@@ -0,0 +1,65 @@
+use std::{fs, mem, path::PathBuf};
+
+use crate::{
+    compiler::{CompilationContext, errors::CompilerError, parser::Parser},
+    scanner::{Scanner, token::TokenType},
+};
+
+impl<'a> CompilationContext<'a> {
+    /// Compiles `import "path";`. Imported file is compiled into the current script, as if its code was written in place of the import,
+    /// so its globals are shared with the importing file. Path is relative to the directory of the importing file.
+    pub(super) fn import_statement(&mut self) -> Result<(), CompilerError> {
+        // Imported globals are defined where the import runs, so it's only allowed where globals can be defined
+        if self.stack.len() > 1 || self.statement_depth > self.top_level_depth {
+            return Err(self.construct_token_error(false, "Can only import at top level."));
+        }
+        self.consume(TokenType::String, "Expected file path after 'import'.")?;
+        let import_path = self.string_contents()?;
+        let path = self.resolve_import(&import_path)?;
+
+        if self.files.contains(&path) {
+            let message = format!("Circular import of '{import_path}'.");
+            return Err(self.construct_token_error(false, &message));
+        }
+        let source = fs::read_to_string(&path).map_err(|e| {
+            let message = format!("Can't import '{import_path}': {e}.");
+            self.construct_token_error(false, &message)
+        })?;
+        self.consume(TokenType::Semicolon, "Expected ';' after import.")?;
+
+        // SAFETY: Imported source is only borrowed by `source` and `parser` of this context while it's compiled, and
+        // `sources` are freed with the context
+        let source = unsafe { self.sources.add(source) };
+        self.compile_import(path, source).map_err(|e| {
+            CompilerError::ExpressionError(format!("In imported file '{import_path}': {e}"))
+        })
+    }
+
+    /// Returns canonical path of an imported file, relative to the directory of the file importing it
+    fn resolve_import(&mut self, import_path: &str) -> Result<PathBuf, CompilerError> {
+        let path = match self.files.last().and_then(|file| file.parent()) {
+            Some(directory) => directory.join(import_path),
+            None => PathBuf::from(import_path),
+        };
+        path.canonicalize().map_err(|e| {
+            let message = format!("Can't import '{import_path}': {e}.");
+            self.construct_token_error(false, &message)
+        })
+    }
+
+    /// Compiles all declarations of an imported file, then switches back to the importing file, right after the import statement
+    fn compile_import(&mut self, path: PathBuf, source: &'a str) -> Result<(), CompilerError> {
+        let parser = mem::replace(&mut self.parser, Parser::new(Scanner::new(source)));
+        let importing_source = mem::replace(&mut self.source, source);
+        self.files.push(path);
+        let top_level_depth = mem::replace(&mut self.top_level_depth, self.statement_depth + 1);
+
+        let result = self.compile_declarations();
+
+        self.top_level_depth = top_level_depth;
+        self.files.pop();
+        self.source = importing_source;
+        self.parser = parser;
+        result
+    }
+}
@@ -1,4 +1,4 @@
-use std::num::ParseFloatError;
+use std::{num::ParseFloatError, str::Chars};
 
 use crate::{
     chunk::OpCode,
@@ -9,6 +9,11 @@ use crate::{
 
 impl CompilationContext<'_> {
     pub(super) fn number(&mut self, _: bool) -> Result<(), CompilerError> {
+        self.number_literal(false)
+    }
+
+    /// Emits the number literal of previous token as a constant, negated if `negate` is true
+    pub(super) fn number_literal(&mut self, negate: bool) -> Result<(), CompilerError> {
         let error = self.construct_token_error(false, "Expected Number, found None");
         // Get previous token, which should be a number
         let token = self.parser.previous.as_ref().ok_or(error)?;
@@ -19,6 +24,7 @@ impl CompilationContext<'_> {
             .parse()
             .map_err(|e: ParseFloatError| self.construct_token_error(false, &e.to_string()))?;
 
+        let val = if negate { -val } else { val };
         // Write this in chunk
         self.emit_constant(val.into())?;
 
@@ -39,6 +45,17 @@ impl CompilationContext<'_> {
     }
 
     pub(super) fn string(&mut self, _: bool) -> Result<(), CompilerError> {
+        let str = self.string_contents()?;
+        // Create a Value object from String
+        let value = Value::from(str);
+        // Emit that value as constant
+        self.emit_constant(value)?;
+
+        Ok(())
+    }
+
+    /// Returns contents of the previous string token, without quotes and with escape sequences replaced
+    pub(super) fn string_contents(&mut self) -> Result<String, CompilerError> {
         let error = self.construct_token_error(false, "Expected token");
         let token = self.parser.previous.as_ref().ok_or(error)?;
         // Skip the double quotes character '"'
@@ -46,13 +63,86 @@ impl CompilationContext<'_> {
         // Last index of token would be `length - 1`, and has ending double quotes
         // So, also skipping ending '"'
         let end_index = start_index + (token.length - 2);
-        // String value from source code is getting copied into virtual machine
-        let str = self.source[start_index..end_index].to_owned();
-        // Create a Value object from String
-        let value = Value::from(str);
-        // Emit that value as constant
-        self.emit_constant(value)?;
+        // String value from source code is getting copied into virtual machine, with escape sequences replaced
+        let source = self.source;
+        self.decode_escapes(&source[start_index..end_index])
+    }
 
-        Ok(())
+    /// Replaces escape sequences, like `\n` or `\u{1F600}`, by the characters they represent
+    fn decode_escapes(&mut self, raw: &str) -> Result<String, CompilerError> {
+        let mut decoded = String::with_capacity(raw.len());
+        let mut chars = raw.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                decoded.push(c);
+                continue;
+            }
+            // Character after '\' decides what needs to be inserted
+            match chars.next() {
+                Some('n') => decoded.push('\n'),
+                Some('t') => decoded.push('\t'),
+                Some('r') => decoded.push('\r'),
+                Some('0') => decoded.push('\0'),
+                Some('\\') => decoded.push('\\'),
+                Some('"') => decoded.push('"'),
+                // Line continuation, string goes on from the next line without a line break
+                Some('\n') => {}
+                Some('\r') if chars.next() == Some('\n') => {}
+                Some('u') => {
+                    let c = self.unicode_escape(&mut chars)?;
+                    decoded.push(c);
+                }
+                Some('x') => {
+                    let c = self.hex_escape(&mut chars)?;
+                    decoded.push(c);
+                }
+                _ => return Err(self.construct_token_error(false, "Invalid escape sequence.")),
+            }
+        }
+
+        Ok(decoded)
+    }
+
+    /// Decodes `NN` part of a `\xNN` escape. Should have exactly 2 hex digits, and gives the ASCII or Latin-1 character with that code.
+    fn hex_escape(&mut self, chars: &mut Chars) -> Result<char, CompilerError> {
+        let mut code = 0u8;
+        for _ in 0..2 {
+            // Safe to cast, hex digit is at most 15
+            #[allow(clippy::cast_possible_truncation)]
+            let digit =
+                chars.next().and_then(|c| c.to_digit(16)).ok_or_else(|| {
+                    self.construct_token_error(false, "Invalid hex escape sequence.")
+                })? as u8;
+            code = code * 16 + digit;
+        }
+        Ok(char::from(code))
+    }
+
+    /// Decodes `{XXXX}` part of a `\u{XXXX}` escape. Can have 1 to 6 hex digits.
+    fn unicode_escape(&mut self, chars: &mut Chars) -> Result<char, CompilerError> {
+        if chars.next() != Some('{') {
+            return Err(self.construct_token_error(false, "Expected '{' after '\\u'."));
+        }
+
+        let mut hex = String::new();
+        loop {
+            match chars.next() {
+                Some('}') if !hex.is_empty() => break,
+                Some(c) if c.is_ascii_hexdigit() && hex.len() < 6 => hex.push(c),
+                _ => {
+                    return Err(
+                        self.construct_token_error(false, "Invalid unicode escape sequence.")
+                    );
+                }
+            }
+        }
+
+        // At most 6 hex digits, so it always fits in `u32`
+        let code_point = u32::from_str_radix(&hex, 16)
+            .map_err(|e| self.construct_token_error(false, &e.to_string()))?;
+        // Surrogates and values above `0x10FFFF` aren't valid characters
+        char::from_u32(code_point)
+            .ok_or_else(|| self.construct_token_error(false, "Invalid unicode code point."))
     }
 }
@@ -2,10 +2,12 @@
 /// This module is responsible of taking source code, parse it and generate bytecode. This is a single phase compiler. It means it parses code and and generate bytecode in one step
 /// Byte code is generated as soon as an expression has been parsed. This module shouldn't care about object values created at runtime. Like strings can be created at runtime and you can also specify a string as literal. They both should behave differently, and string created at runtime should be garbage collected.
 ///
+use std::path::{Path, PathBuf};
+
 use crate::{
     chunk::Chunk,
-    compiler::{errors::CompilerError, parser::Parser, types::FunctionType},
-    constants::UINT8_COUNT,
+    compiler::{errors::CompilerError, parser::Parser, sources::Sources, types::FunctionType},
+    constants::{MAX_NESTING_DEPTH, UINT8_COUNT},
     scanner::{
         Scanner,
         token::{Token, TokenType},
@@ -13,19 +15,18 @@ use crate::{
     value::{Value, objects::FunctionObject},
 };
 
-#[cfg(feature = "debug_trace_execution")]
-use crate::debug::Debug;
-
 mod bytecode;
 mod declarations;
 pub mod errors;
 mod expressions;
 mod functions;
+mod imports;
 mod literals;
 mod operations;
 pub mod parser;
 mod precedence;
 mod scope;
+mod sources;
 mod statements;
 #[cfg(test)]
 mod tests;
@@ -38,12 +39,52 @@ pub struct Local {
     depth: i32,
 }
 
+/// A call whose result is returned as it is by the function making it, like `return f(x);`. Such a call could reuse the frame
+/// of its caller, instead of pushing a new one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TailCall {
+    /// Name of the function making the call. `None` for the top-level script and anonymous functions.
+    pub function: Option<String>,
+    /// Offset of the `OpCall` instruction in the chunk of that function
+    pub offset: usize,
+}
+
 pub struct CompilationContext<'a> {
     /// Reference of the source code
     source: &'a str,
     /// Parser object to parse code on demand
     parser: Parser<'a>,
     stack: Vec<CompilerState>,
+    /// Collect warnings about suspicious code. Off by default
+    warnings_enabled: bool,
+    /// Warnings collected while compiling, formatted with line numbers
+    warnings: Vec<String>,
+    /// Whether expression being compiled calls a function or assigns a variable
+    has_side_effect: bool,
+    /// How deep the statement being compiled is nested in other statements. Top-level statements have depth 1.
+    statement_depth: usize,
+    /// `statement_depth` of top-level statements of the file being compiled. Statements of an imported file are nested in the import statement.
+    top_level_depth: usize,
+    /// How deep statements, function bodies and expressions are currently nested. Compiler is recursive, so it's limited to avoid stack overflow.
+    nesting_depth: usize,
+    /// Maximum allowed `nesting_depth`
+    max_nesting_depth: usize,
+    /// Files being compiled, from the outermost one to the one currently imported. Used to resolve relative paths and detect circular imports.
+    /// Empty when source doesn't come from a file, e.g. in REPL.
+    files: Vec<PathBuf>,
+    /// `nesting_depth` of the operand of `return` statement being compiled, if any. See `in_tail_position`.
+    return_operand_depth: Option<usize>,
+    /// Calls compiled in tail position so far
+    tail_calls: Vec<TailCall>,
+    /// Compile top-level function declarations before other code, so they can be called before they're declared. Off by default
+    hoist_functions: bool,
+    /// Number of the first line of `source`, 1 unless source was passed with `extend_from_line`
+    first_line: i32,
+    /// Offsets of `fun` keywords of top-level function declarations, which have been compiled ahead of other code
+    hoisted_functions: Vec<usize>,
+    /// Sources owned by the context, like imported files and source passed to `extend_owned`. `source` and `parser` may
+    /// borrow from them, so they're only freed when the context is dropped, or extended with new owned source.
+    sources: Sources,
 }
 
 impl<'a> CompilationContext<'a> {
@@ -57,15 +98,92 @@ impl<'a> CompilationContext<'a> {
             stack: Vec::new(),
             source,
             parser,
+            warnings_enabled: false,
+            warnings: Vec::new(),
+            has_side_effect: false,
+            statement_depth: 0,
+            top_level_depth: 1,
+            nesting_depth: 0,
+            max_nesting_depth: MAX_NESTING_DEPTH,
+            files: Vec::new(),
+            return_operand_depth: None,
+            tail_calls: Vec::new(),
+            hoist_functions: false,
+            hoisted_functions: Vec::new(),
+            first_line: 1,
+            sources: Sources::default(),
         }
     }
 
+    /// Sets path of the file source code was read from. Imports in it are resolved relative to its directory, otherwise relative to the current directory.
+    pub fn set_file_path(&mut self, path: &Path) {
+        // Same file can be reached by different relative paths, canonical path makes circular imports detectable
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.files = vec![path];
+    }
+
+    /// Enables or disables warnings, like expression statements having no effect. Warnings don't stop compilation.
+    pub fn set_warnings(&mut self, enabled: bool) {
+        self.warnings_enabled = enabled;
+    }
+
+    /// Sets how deep statements, function bodies and expressions can be nested, 250 by default.
+    /// Going deeper is a compile error instead of a stack overflow, which is useful when compiling untrusted code.
+    pub fn set_max_nesting_depth(&mut self, depth: usize) {
+        self.max_nesting_depth = depth;
+    }
+
+    /// Enables or disables hoisting of top-level function declarations. When enabled, they're compiled before the rest of the
+    /// script, so `f(); fun f() {}` works, like the function was declared first. Functions declared in blocks, other
+    /// functions and imported files aren't hoisted.
+    pub fn set_hoist_functions(&mut self, enabled: bool) {
+        self.hoist_functions = enabled;
+    }
+
+    /// Returns the warnings collected so far, and clears them
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Returns calls compiled in tail position so far, and clears them. Useful for tools analyzing bytecode, and optimizations.
+    pub fn take_tail_calls(&mut self) -> Vec<TailCall> {
+        std::mem::take(&mut self.tail_calls)
+    }
+
+    /// Prepares the context to compile more source code, e.g. next line in REPL.
+    ///
+    /// Parser state is always reset, tokens of previous source are not valid for the new source.
+    /// The stack of `CompilerState` is kept. A successful `compile` pops the top-level script from the stack and failed one clears it,
+    /// so when the stack is empty, a fresh top-level script is pushed to compile new source into.
+    /// Globals aren't tracked by the compiler, they're resolved by name at runtime, so redefining them in next source is fine.
     pub fn extend(&mut self, source: &'a str) {
-        let scanner: Scanner<'_> = Scanner::new(source);
+        self.extend_from_line(source, 1);
+    }
+
+    /// Same as `extend`, but lines of `source` are numbered from `first_line`. REPL passes the line of the session source
+    /// was typed on, so compile errors, and runtime errors of every function declared in it, point at that line.
+    pub fn extend_from_line(&mut self, source: &'a str, first_line: i32) {
+        let scanner: Scanner<'_> = Scanner::starting_at_line(source, first_line);
         // Parser needs to scan tokens on demand, it'll need scanner object for that
         let parser = Parser::new(scanner);
         self.source = source;
         self.parser = parser;
+        self.first_line = first_line;
+
+        if self.stack.is_empty() {
+            self.push(CompilerState::new(FunctionType::default_script()));
+        }
+    }
+
+    /// Same as `extend_from_line`, but the context owns `source`, so the caller doesn't have to keep it alive, e.g. lines
+    /// typed in REPL. Sources owned before, and files they imported, are freed, compiling new source doesn't need them.
+    pub fn extend_owned(&mut self, source: String, first_line: i32) {
+        // SAFETY: Compiled functions own their data, so only `source` and `parser` can borrow from owned sources. They're
+        // replaced right below, before they're used again.
+        unsafe { self.sources.clear() };
+        // SAFETY: Source is only borrowed by this context, and freed with it, or by the next call
+        let source = unsafe { self.sources.add(source) };
+        self.extend_from_line(source, first_line);
     }
 
     pub fn push(&mut self, compiler: CompilerState) {
@@ -104,6 +222,39 @@ impl<'a> CompilationContext<'a> {
     ///
     /// It will return errors when there are syntax errors and compiler can't proceed further
     pub fn compile(&mut self) -> Result<Value, CompilerError> {
+        self.compile_script().map(Value::from)
+    }
+
+    /// Same as `compile`, but returns the top-level function itself, instead of a `Value` pointing to it
+    /// # Errors
+    ///
+    /// It will return errors when there are syntax errors and compiler can't proceed further
+    pub fn compile_script(&mut self) -> Result<FunctionObject, CompilerError> {
+        let result = self.compile_source();
+        if result.is_err() {
+            // Discard partially compiled functions, so context can be extended with new source
+            self.stack.clear();
+            self.statement_depth = 0;
+            self.top_level_depth = 1;
+            self.nesting_depth = 0;
+            self.return_operand_depth = None;
+        }
+        result
+    }
+
+    fn compile_source(&mut self) -> Result<FunctionObject, CompilerError> {
+        self.hoisted_functions.clear();
+        if self.hoist_functions {
+            self.compile_hoisted_functions()?;
+            // Start over, to compile the rest of the script
+            self.parser = Parser::new(Scanner::starting_at_line(self.source, self.first_line));
+        }
+        self.compile_declarations()?;
+        self.end_function()
+    }
+
+    /// Compiles declarations until the end of the source
+    fn compile_declarations(&mut self) -> Result<(), CompilerError> {
         // Consumes first token
         // Important because we look back and see previous tokens
         self.parser.advance().map_err(CompilerError::ParserError)?;
@@ -112,8 +263,7 @@ impl<'a> CompilationContext<'a> {
             // Process statements
             self.declaration()?;
         }
-
-        self.end_compiler()
+        Ok(())
     }
 
     fn consume(&mut self, ty: TokenType, message: &str) -> Result<(), CompilerError> {
@@ -155,6 +305,11 @@ impl<'a> CompilationContext<'a> {
 
     /// Executes when all expressions are evaluated
     fn end_compiler(&mut self) -> Result<Value, CompilerError> {
+        self.end_function().map(Value::from)
+    }
+
+    /// Finishes the function being compiled and pops its `CompilerState`
+    fn end_function(&mut self) -> Result<FunctionObject, CompilerError> {
         self.emit_return()?;
 
         let func = &mut self.compiler_mut().function_type;
@@ -164,18 +319,10 @@ impl<'a> CompilationContext<'a> {
 
         // Disassembles byte code to see what's going on
         #[cfg(feature = "debug_trace_execution")]
-        {
-            let name = if let Some(name) = &fun_obj.name {
-                name
-            } else {
-                "<script>"
-            };
-
-            Debug::dissassemble_chunk(&self.compiler().chunk(), name);
-        }
+        print!("{}", fun_obj.disassemble());
 
         let _ = self.pop();
-        Ok(fun_obj.into())
+        Ok(fun_obj)
     }
 }
 
@@ -39,6 +39,17 @@ impl CompilationContext<'_> {
         self.patch_jump(end_jump)
     }
 
+    /// Compiles `a ?? b`, which is `a` if it's not `nil`, otherwise `b`. Unlike `or`, `false` is kept.
+    pub(super) fn coalesce(&mut self, _: bool) -> Result<(), CompilerError> {
+        // Left expression is on the stack. If it's not nil, it's the result, so right expression is skipped.
+        let end_jump = self.emit_jump(OpCode::OpJumpIfNotNil as u8)?;
+        // Left expression is nil, discard it and evaluate the right one instead
+        self.emit_byte(OpCode::OpPop as u8)?;
+        // Same precedence makes chains group to the right, `a ?? (b ?? c)`, so first non-nil value is the result
+        self.parse_precedence(Precedence::Coalesce)?;
+        self.patch_jump(end_jump)
+    }
+
     /// Writes byte code for binary instructions
     pub(super) fn binary(&mut self, _: bool) -> Result<(), CompilerError> {
         // Get binary operator
@@ -52,6 +63,54 @@ impl CompilationContext<'_> {
         // have higher precedence, only prefix rule will get called and then function will return
         self.parse_precedence(Precedence::from((rule.precedence as u8) + 1))?;
 
+        if Self::is_comparison(operator) && self.next_is_comparison()? {
+            return self.chained_comparison(operator);
+        }
+        self.emit_binary_operator(operator)
+    }
+
+    /// Compiles comparisons following each other, like `a < b < c`, as `a < b and b < c`. Operands in the middle are evaluated
+    /// once. Left operand and right operand of the first comparison have already been compiled.
+    fn chained_comparison(&mut self, mut operator: TokenType) -> Result<(), CompilerError> {
+        let mut false_jumps = vec![];
+        loop {
+            if !self.next_is_comparison()? {
+                // Last comparison gives the result of the whole chain
+                self.emit_binary_operator(operator)?;
+                break;
+            }
+            // Right operand is kept under the result, as left operand of the next comparison
+            self.emit_byte(OpCode::OpTuck as u8)?;
+            self.emit_binary_operator(operator)?;
+            false_jumps.push(self.emit_jump(OpCode::OpPopJumpIfFalse as u8)?);
+
+            self.parser.advance().map_err(CompilerError::ParserError)?;
+            operator = self.get_previous_token_ty()?;
+            self.parse_precedence(Precedence::Term)?;
+        }
+
+        let end_jump = self.emit_jump(OpCode::OpJump as u8)?;
+        for jump in false_jumps {
+            self.patch_jump(jump)?;
+        }
+        // A comparison was false. Its right operand is still on the stack, and whole chain is false.
+        self.emit_bytes(OpCode::OpPop as u8, OpCode::OpFalse as u8)?;
+        self.patch_jump(end_jump)
+    }
+
+    fn is_comparison(operator: TokenType) -> bool {
+        matches!(
+            operator,
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual
+        )
+    }
+
+    fn next_is_comparison(&mut self) -> Result<bool, CompilerError> {
+        Ok(Self::is_comparison(self.get_current_token_ty()?))
+    }
+
+    /// Emits the instruction of a binary operator, whose operands are on the stack
+    fn emit_binary_operator(&mut self, operator: TokenType) -> Result<(), CompilerError> {
         // Check which binary operator is this, and emit byte code accordingly
         match operator {
             TokenType::Plus => self.emit_byte(OpCode::OpAdd as u8)?,
@@ -61,13 +120,9 @@ impl CompilationContext<'_> {
             TokenType::BangEqual => self.emit_bytes(OpCode::OpEqual as u8, OpCode::OpNot as u8)?,
             TokenType::EqualEqual => self.emit_byte(OpCode::OpEqual as u8)?,
             TokenType::Greater => self.emit_byte(OpCode::OpGreater as u8)?,
-            TokenType::GreaterEqual => {
-                self.emit_bytes(OpCode::OpLess as u8, OpCode::OpNot as u8)?;
-            }
+            TokenType::GreaterEqual => self.emit_byte(OpCode::OpGreaterEqual as u8)?,
             TokenType::Less => self.emit_byte(OpCode::OpLess as u8)?,
-            TokenType::LessEqual => {
-                self.emit_bytes(OpCode::OpGreater as u8, OpCode::OpNot as u8)?;
-            }
+            TokenType::LessEqual => self.emit_byte(OpCode::OpLessEqual as u8)?,
             // There isn't any other binary operator allowed
             _ => unreachable!(),
         }
@@ -80,6 +135,12 @@ impl CompilationContext<'_> {
         // Get operator
         let operator = self.get_previous_token_ty()?;
 
+        // `-5` is compiled as a single negative constant, instead of `5` negated at runtime. Only a number literal itself is folded,
+        // not variables or other expressions. Operators binding tighter than unary, `.` and calls, fail on numbers anyway.
+        if operator == TokenType::Minus && self.match_curr_ty(TokenType::Number)? {
+            return self.number_literal(true);
+        }
+
         // Recursive call to get the operand
         // In normal case, bytes for the Number operand will get emitted
         self.parse_precedence(Precedence::Unary)?;
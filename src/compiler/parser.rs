@@ -113,8 +113,8 @@ impl<'a> Parser<'a> {
     /// This method is important because it formats error nicely with line numbers
     fn construct_error(&self, token: &Token, message: &str) -> ParserError {
         let mut err_msg = String::new();
-        // Get line information from token and add to the message
-        let _ = write!(err_msg, "[line {}] Error", token.line);
+        // Get line and column information from token and add to the message
+        let _ = write!(err_msg, "[line {}:{}] Error", token.line, token.column);
 
         // Check if we've reached at the end
         if token.ty == TokenType::Eof {
@@ -125,7 +125,7 @@ impl<'a> Parser<'a> {
             // C implementation is different and that's not how we handle errors in Rust
         } else {
             // Gets invalid/problematic token and append to the error message
-            let _ = write!(err_msg, " at '{}'", token.as_str(self.scanner.source));
+            let _ = write!(err_msg, " at '{}'", token.lexeme(self.scanner.source));
         }
         // Push the custom message at the end
         let _ = writeln!(err_msg, ": {message}");
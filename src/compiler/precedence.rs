@@ -11,6 +11,7 @@ use crate::{
 pub enum Precedence {
     None,
     Assignment, // =
+    Coalesce,   // ??
     Or,         // or
     And,        // and
     Equality,   // == !=
@@ -28,15 +29,16 @@ impl From<u8> for Precedence {
         match value {
             0 => Self::None,
             1 => Self::Assignment,
-            2 => Self::Or,
-            3 => Self::And,
-            4 => Self::Equality,
-            5 => Self::Comparison,
-            6 => Self::Term,
-            7 => Self::Factor,
-            8 => Self::Unary,
-            9 => Self::Call,
-            10 => Self::Primary,
+            2 => Self::Coalesce,
+            3 => Self::Or,
+            4 => Self::And,
+            5 => Self::Equality,
+            6 => Self::Comparison,
+            7 => Self::Term,
+            8 => Self::Factor,
+            9 => Self::Unary,
+            10 => Self::Call,
+            11 => Self::Primary,
             _ => unreachable!(),
         }
     }
@@ -63,7 +65,7 @@ impl<'a> ParseRule<'a> {
     /// Another considerable option is to use `HashMap` to store by `TokenyType`, which
     /// would be explored in future
     #[allow(clippy::too_many_lines)]
-    fn get_rules() -> [ParseRule<'a>; 40] {
+    fn get_rules() -> [ParseRule<'a>; 48] {
         [
             // TokenType::LeftParen
             ParseRule {
@@ -94,18 +96,39 @@ impl<'a> ParseRule<'a> {
                 infix: None,
                 precedence: Precedence::None,
             },
+            // TokenType::LeftBracket
+            ParseRule {
+                // Array literal, like `[1, 2, 3]`
+                prefix: Some(CompilationContext::array),
+                // Indexing, like `a[0]`
+                infix: Some(CompilationContext::index),
+                precedence: Precedence::Call,
+            },
+            // TokenType::RightBracket
+            ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             // TokenType::Comma
             ParseRule {
                 prefix: None,
                 infix: None,
                 precedence: Precedence::None,
             },
-            // TokenType::Dot
+            // TokenType::Colon
             ParseRule {
                 prefix: None,
                 infix: None,
                 precedence: Precedence::None,
             },
+            // TokenType::Dot
+            ParseRule {
+                prefix: None,
+                // Property access, like `"hello".length`
+                infix: Some(CompilationContext::dot),
+                precedence: Precedence::Call,
+            },
             // TokenType::Minus
             ParseRule {
                 // If it involves only one operand, it's a prefix and is unary operation
@@ -189,6 +212,12 @@ impl<'a> ParseRule<'a> {
                 infix: Some(CompilationContext::binary),
                 precedence: Precedence::Comparison,
             },
+            // TokenType::QuestionQuestion
+            ParseRule {
+                prefix: None,
+                infix: Some(CompilationContext::coalesce),
+                precedence: Precedence::Coalesce,
+            },
             // TokenType::Identifier
             ParseRule {
                 prefix: Some(CompilationContext::variable),
@@ -240,6 +269,13 @@ impl<'a> ParseRule<'a> {
                 precedence: Precedence::None,
             },
             // TokenType::Fun
+            ParseRule {
+                // Function expression, like `var add = fun (a, b) { return a + b; };`
+                prefix: Some(CompilationContext::function_expression),
+                infix: None,
+                precedence: Precedence::None,
+            },
+            // TokenType::Global
             ParseRule {
                 prefix: None,
                 infix: None,
@@ -251,6 +287,12 @@ impl<'a> ParseRule<'a> {
                 infix: None,
                 precedence: Precedence::None,
             },
+            // TokenType::Import
+            ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             // TokenType::Nil
             ParseRule {
                 prefix: Some(CompilationContext::literal),
@@ -269,6 +311,12 @@ impl<'a> ParseRule<'a> {
                 infix: None,
                 precedence: Precedence::None,
             },
+            // TokenType::Repeat
+            ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             // TokenType::Return
             ParseRule {
                 prefix: None,
@@ -293,6 +341,12 @@ impl<'a> ParseRule<'a> {
                 infix: None,
                 precedence: Precedence::None,
             },
+            // TokenType::Until
+            ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             // TokenType::Var
             ParseRule {
                 prefix: None,
@@ -9,19 +9,42 @@ impl CompilationContext<'_> {
         self.compiler_mut().scope_depth += 1;
     }
 
+    /// Called before compiling something nested, like a statement or an expression. Returns error if it's nested too deep.
+    pub(super) fn enter_nesting(&mut self) -> Result<(), CompilerError> {
+        if self.nesting_depth >= self.max_nesting_depth {
+            return Err(self.construct_token_error(false, "Code is nested too deeply."));
+        }
+        self.nesting_depth += 1;
+        Ok(())
+    }
+
+    pub(super) fn exit_nesting(&mut self) {
+        self.nesting_depth -= 1;
+    }
+
     pub(super) fn end_scope(&mut self) -> Result<(), CompilerError> {
         self.compiler_mut().scope_depth -= 1;
 
+        let mut count: usize = 0;
         while !self.compiler().locals.is_empty()
             && self.compiler().locals[self.compiler().locals.len() - 1].depth
                 > self.compiler().scope_depth
         {
-            self.emit_byte(OpCode::OpPop as u8)?;
-            // self.compiler_mut().local_count -= 1;
+            count += 1;
             self.compiler_mut().locals.pop();
         }
 
-        Ok(())
+        // Locals going out of scope are on top of the stack, pop them all with a single instruction
+        match count {
+            0 => Ok(()),
+            1 => self.emit_byte(OpCode::OpPop as u8),
+            _ => {
+                let count = u8::try_from(count).map_err(|_| {
+                    self.construct_token_error(false, "Too many local variables in scope.")
+                })?;
+                self.emit_bytes(OpCode::OpPopN as u8, count)
+            }
+        }
     }
 
     pub(super) fn block(&mut self) -> Result<(), CompilerError> {
@@ -0,0 +1,43 @@
+use std::ptr::NonNull;
+
+/// Source code owned by `CompilationContext`, like imported files and lines typed in REPL. Compiler borrows source code for
+/// as long as it runs, so sources it owns are kept here, and freed with the context, instead of being leaked.
+#[derive(Debug, Default)]
+pub(super) struct Sources {
+    /// Kept as raw pointers, so references handed out stay valid when the vector grows, or the context is moved
+    sources: Vec<NonNull<str>>,
+}
+
+impl Sources {
+    /// Takes ownership of `source` and returns a reference to it, valid until sources are cleared or dropped
+    ///
+    /// # Safety
+    ///
+    /// Returned reference must not be used after `clear` is called, or `Sources` is dropped. It's up to the caller to pick a
+    /// lifetime which doesn't outlive them.
+    pub(super) unsafe fn add<'a>(&mut self, source: String) -> &'a str {
+        let pointer = NonNull::from(Box::leak(source.into_boxed_str()));
+        self.sources.push(pointer);
+        // SAFETY: Pointer comes from a live `Box`, which is only freed by `clear`
+        unsafe { pointer.as_ref() }
+    }
+
+    /// Frees all sources
+    ///
+    /// # Safety
+    ///
+    /// No reference returned by `add` may be used after this
+    pub(super) unsafe fn clear(&mut self) {
+        for pointer in self.sources.drain(..) {
+            // SAFETY: Pointer was created from a `Box` in `add`, and is freed only once, since it's drained
+            drop(unsafe { Box::from_raw(pointer.as_ptr()) });
+        }
+    }
+}
+
+impl Drop for Sources {
+    fn drop(&mut self) {
+        // SAFETY: Owner of `Sources` drops references with it, they're never used after
+        unsafe { self.clear() };
+    }
+}
@@ -7,6 +7,15 @@ use crate::{
 impl CompilationContext<'_> {
     /// Evaluates statements
     pub(super) fn statement(&mut self) -> Result<(), CompilerError> {
+        self.enter_nesting()?;
+        self.statement_depth += 1;
+        self.nested_statement()?;
+        self.statement_depth -= 1;
+        self.exit_nesting();
+        Ok(())
+    }
+
+    fn nested_statement(&mut self) -> Result<(), CompilerError> {
         if self.match_curr_ty(TokenType::Print)? {
             self.print_statement()?;
         } else if self.match_curr_ty(TokenType::For)? {
@@ -15,8 +24,14 @@ impl CompilationContext<'_> {
             self.if_statement()?;
         } else if self.match_curr_ty(TokenType::Return)? {
             self.return_statement()?;
+        } else if self.match_curr_ty(TokenType::Import)? {
+            self.import_statement()?;
         } else if self.match_curr_ty(TokenType::While)? {
             self.while_statement()?;
+        } else if self.match_curr_ty(TokenType::Repeat)? {
+            self.repeat_statement()?;
+        } else if self.match_curr_ty(TokenType::Global)? {
+            self.global_statement()?;
         } else if self.match_curr_ty(TokenType::LeftBrace)? {
             self.begin_scope();
             self.block()?;
@@ -28,31 +43,42 @@ impl CompilationContext<'_> {
     }
 
     fn if_statement(&mut self) -> Result<(), CompilerError> {
-        // 'if' token already consumed, now consume '('
-        self.consume(TokenType::LeftParen, "Expected '(' after 'if'")?;
-        // Evaluate condition expression and put the result on stack
-        self.expression()?;
-        // Consume the ')', after evaluation
-        self.consume(TokenType::RightParen, "Expected ')' after condition")?;
-        // If condition fails, then we'll need to skip the 'then' block. For this purpose, 'OpJumpIfFalse' needs to be emitted with distance/number of bytes we need to skip. To skip 65,535 bytes, we need to reserve two bytes. `emit_jump` will also emit these two place holder bytes alongside the OpCode.
-        let then_jump = self.emit_jump(OpCode::OpJumpIfFalse as u8)?;
-        // Emit opcode to pop the condition if the condition is true. This is important before emitting the bytecode for statements of if block.
-        self.emit_byte(OpCode::OpPop as u8)?;
-        // Evaluate the 'then' block
-        self.statement()?;
-        // Emit instructions for jumping to a specific position. Currently will contain placeholder bytes alongside the `OpJump` opcode
-        let else_jump = self.emit_jump(OpCode::OpJump as u8)?;
-        // `then_jump` will get patched. It means, we have evaluated the 'if' block code, we'll update the distance bytes, i.e. how far we actually need to jump when 'if' condition fails
-        // Till now, we've evaluated and generated byte code for 'if' (3 bytes), 'if block' (gets executed when condition is true), and 'else' (3 bytes). When 'if' condition is false, it will skip else bytecode. It's all linear array of bytes, so we will skip right after `else`'s 3 bytes.
-        self.patch_jump(then_jump)?;
-        // Assuming if condition is false, and code is jumped to after `else`'s 3 bytes, we need to pop the condition result from the stack.
-        self.emit_byte(OpCode::OpPop as u8)?;
-        // Evaluate the else block
-        if self.match_curr_ty(TokenType::Else)? {
+        // Every branch jumps to the end of the whole chain. `else if` is compiled in the same loop, instead of as a nested statement,
+        // so all of these jumps are patched once at the end, and long chains don't nest deeper.
+        let mut end_jumps = vec![];
+        loop {
+            // 'if' token already consumed, now consume '('
+            self.consume(TokenType::LeftParen, "Expected '(' after 'if'")?;
+            // Evaluate condition expression and put the result on stack
+            self.expression()?;
+            // Consume the ')', after evaluation
+            self.consume(TokenType::RightParen, "Expected ')' after condition")?;
+            // If condition fails, then we'll need to skip the 'then' block. For this purpose, 'OpPopJumpIfFalse' needs to be emitted with distance/number of bytes we need to skip. To skip 65,535 bytes, we need to reserve two bytes. `emit_jump` will also emit these two place holder bytes alongside the OpCode.
+            // Condition is popped by the same instruction, whether the jump is performed or not.
+            let then_jump = self.emit_jump(OpCode::OpPopJumpIfFalse as u8)?;
+            // Evaluate the 'then' block
             self.statement()?;
+            // Emit instructions for jumping to the end of the chain. Currently will contain placeholder bytes alongside the `OpJump` opcode
+            end_jumps.push(self.emit_jump(OpCode::OpJump as u8)?);
+            // `then_jump` will get patched. It means, we have evaluated the 'if' block code, we'll update the distance bytes, i.e. how far we actually need to jump when 'if' condition fails
+            // Till now, we've evaluated and generated byte code for 'if' (3 bytes), 'if block' (gets executed when condition is true), and 'else' (3 bytes). When 'if' condition is false, it will skip else bytecode. It's all linear array of bytes, so we will skip right after `else`'s 3 bytes.
+            self.patch_jump(then_jump)?;
+
+            if !self.match_curr_ty(TokenType::Else)? {
+                break;
+            }
+            // Next condition of the chain
+            if self.match_curr_ty(TokenType::If)? {
+                continue;
+            }
+            // Evaluate the else block
+            self.statement()?;
+            break;
+        }
+        // Jumps should only be patched after evaluation of the else block. No `OpPop` needed because else doesn't have any condition
+        for jump in end_jumps {
+            self.patch_jump(jump)?;
         }
-        // Else jump should only be patched after evaluation of the else block. No `OpPop` needed because else doesn't have any condition
-        self.patch_jump(else_jump)?;
 
         Ok(())
     }
@@ -63,13 +89,33 @@ impl CompilationContext<'_> {
         self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition")?;
 
-        let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse as u8)?;
-        self.emit_byte(OpCode::OpPop as u8)?;
+        // Condition is popped when it's checked, so nothing is left on the stack after exiting the loop
+        let exit_jump = self.emit_jump(OpCode::OpPopJumpIfFalse as u8)?;
         self.statement()?;
         self.emit_loop(loop_start)?;
 
-        self.patch_jump(exit_jump)?;
-        self.emit_byte(OpCode::OpPop as u8)
+        self.patch_jump(exit_jump)
+    }
+
+    /// Compiles `repeat <body> until (<condition>);`. Body runs at least once, and loop ends when condition becomes true.
+    fn repeat_statement(&mut self) -> Result<(), CompilerError> {
+        let loop_start = self.compiler().chunk().code.len();
+        self.statement()?;
+
+        self.consume(TokenType::Until, "Expect 'until' after 'repeat' body.")?;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'until'.")?;
+        self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        self.consume(TokenType::Semicolon, "Expect ';' after 'until' condition.")?;
+
+        // Loop continues while condition is falsey, which is the inverse of `while`. `!` only works on booleans and nil,
+        // so instead of negating the condition, falsey one jumps over the exit jump, to the jump back to the start.
+        let repeat_jump = self.emit_jump(OpCode::OpPopJumpIfFalse as u8)?;
+        let exit_jump = self.emit_jump(OpCode::OpJump as u8)?;
+        self.patch_jump(repeat_jump)?;
+        self.emit_loop(loop_start)?;
+
+        self.patch_jump(exit_jump)
     }
 
     fn for_statement(&mut self) -> Result<(), CompilerError> {
@@ -90,8 +136,7 @@ impl CompilationContext<'_> {
         if !self.match_curr_ty(TokenType::Semicolon)? {
             self.expression()?;
             self.consume(TokenType::Semicolon, "Expected ';' after loop condition")?;
-            exit_jump = self.emit_jump(OpCode::OpJumpIfFalse as u8)? as isize;
-            self.emit_byte(OpCode::OpPop as u8)?;
+            exit_jump = self.emit_jump(OpCode::OpPopJumpIfFalse as u8)? as isize;
         }
 
         if !self.match_curr_ty(TokenType::RightParen)? {
@@ -111,7 +156,6 @@ impl CompilationContext<'_> {
 
         if exit_jump != -1 {
             self.patch_jump(exit_jump as usize)?;
-            self.emit_byte(OpCode::OpPop as u8)?;
         }
 
         self.end_scope()
@@ -119,8 +163,25 @@ impl CompilationContext<'_> {
 
     /// Evaluates expression statements. Result of expression statement is discarded at the end of the evaluation. These statements are executed for their side effects, not their produced results.
     fn expression_statement(&mut self) -> Result<(), CompilerError> {
+        // First token of the expression, to point at it in a warning
+        let start = self.parser.current.clone();
+        // Statements can be nested in an expression being compiled, so keep its state
+        let enclosing = std::mem::replace(&mut self.has_side_effect, false);
         // Evaluate the expression
         self.expression()?;
+        // Last statement of the script can omit ';'. Its value becomes the result of the script, instead of being discarded.
+        if self.statement_depth == 1 && self.stack.len() == 1 && self.check_current(TokenType::Eof)
+        {
+            self.has_side_effect = enclosing;
+            return self.emit_byte(OpCode::OpReturn as u8);
+        }
+        // Result is discarded, so expression is useless if it doesn't call or assign anything
+        if !self.has_side_effect
+            && let Some(token) = start
+        {
+            self.warn(&token, "Expression statement has no effect.");
+        }
+        self.has_side_effect = enclosing;
         // Consume the ';' from the end of the expression
         self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
         // Discard the result, because it's not needed.
@@ -139,6 +200,24 @@ impl CompilationContext<'_> {
         Ok(())
     }
 
+    /// Compiles `global x = value;`, which assigns to the global variable `x`, even if a local variable with the same name
+    /// is in scope. Global should already be defined, like with plain assignment.
+    fn global_statement(&mut self) -> Result<(), CompilerError> {
+        self.consume(
+            TokenType::Identifier,
+            "Expected variable name after 'global'.",
+        )?;
+        // Safe to unwrap, identifier has just been consumed
+        let name = self.parser.previous.clone().unwrap();
+        let constant = self.identifier_constant(&name)?;
+        self.consume(TokenType::Equal, "Expected '=' after global variable name.")?;
+        self.expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after value.")?;
+        // Locals aren't resolved, so it's always the global being set. Assignment leaves the value on the stack.
+        self.emit_bytes(OpCode::OpSetGlobal as u8, constant)?;
+        self.emit_byte(OpCode::OpPop as u8)
+    }
+
     fn return_statement(&mut self) -> Result<(), CompilerError> {
         if self.compiler().function_type.is_script() {
             return Err(self.construct_token_error(false, "Can't return from top-level code."));
@@ -147,7 +226,27 @@ impl CompilationContext<'_> {
         if self.match_curr_ty(TokenType::Semicolon)? {
             self.emit_return()
         } else {
-            self.expression()?;
+            // `parse_precedence` compiles the operand one level deeper than the statement
+            let enclosing = self.return_operand_depth.replace(self.nesting_depth + 1);
+            let result = self.expression();
+            // Other values of `return a, b;` are packed in an array, so calls in them aren't in tail position
+            self.return_operand_depth = enclosing;
+            result?;
+            // `return a, b;` returns multiple values, packed in an array
+            if self.match_curr_ty(TokenType::Comma)? {
+                let mut value_count = 1u8;
+                loop {
+                    self.expression()?;
+                    value_count = value_count.checked_add(1).ok_or_else(|| {
+                        self.construct_token_error(false, "Can't return more than 255 values.")
+                    })?;
+
+                    if !self.match_curr_ty(TokenType::Comma)? {
+                        break;
+                    }
+                }
+                self.emit_bytes(OpCode::OpBuildArray as u8, value_count)?;
+            }
             self.consume(TokenType::Semicolon, "Expected ';' after return value.")?;
             self.emit_byte(OpCode::OpReturn as u8)
         }
@@ -1,6 +1,8 @@
 use crate::{
     chunk::OpCode,
-    compiler::{CompilationContext, CompilerState, errors::CompilerError, types::FunctionType},
+    compiler::{
+        CompilationContext, CompilerState, TailCall, errors::CompilerError, types::FunctionType,
+    },
     value::Value,
 };
 
@@ -20,9 +22,8 @@ fn test_var_declaration() {
         OpCode::OpNil as u8,
         // After evaluating expression, check if variable is global or local, by comparing scope_depth. In this case, it's `OpCode::OpSetGlobal`
         OpCode::OpDefineGlobal as u8,
-        0,                      // Index/Position of the value of defined variable on the stack
-        OpCode::OpNil as u8,    // Since it's a top level function, it always returns `Nil`
-        OpCode::OpReturn as u8, // OpCode::OpReturn to stop the virtual machine.
+        0,                         // Index/Position of the value of defined variable on the stack
+        OpCode::OpReturnNil as u8, // Since it's a top level function, it always returns `Nil`
     ];
     assert_eq!(code, &expected_value);
 }
@@ -38,9 +39,8 @@ fn test_var_initialization() {
         2, // Position of constant value in constant pool
         OpCode::OpAdd as u8, // Print Opcode, after expression is evaluated.
         OpCode::OpDefineGlobal as u8,
-        0,                      // Position of variable name in the constant pool
-        OpCode::OpNil as u8,    // Since it's a top level function, it always returns `Nil`
-        OpCode::OpReturn as u8, // OpCode::OpReturn to stop the virtual machine.
+        0,                         // Position of variable name in the constant pool
+        OpCode::OpReturnNil as u8, // Since it's a top level function, it always returns `Nil`
     ];
     assert_eq!(code, &expected_value);
 }
@@ -50,11 +50,10 @@ fn test_print_statement() {
     let fun_obj = compile(r#"print "Hamza";"#).unwrap();
     let code = &fun_obj.as_function_ref().chunk.code;
     let expected_value = vec![
-        OpCode::OpConstant as u8, // Constant OpCode
-        0,                        // Position of constant value in constant pool
-        OpCode::OpPrint as u8,    // Print Opcode, after expression is evaluated.
-        OpCode::OpNil as u8,      // Since it's a top level function, it always returns `Nil`
-        OpCode::OpReturn as u8,   // OpCode::OpReturn to stop the virtual machine.
+        OpCode::OpConstant as u8,  // Constant OpCode
+        0,                         // Position of constant value in constant pool
+        OpCode::OpPrint as u8,     // Print Opcode, after expression is evaluated.
+        OpCode::OpReturnNil as u8, // Since it's a top level function, it always returns `Nil`
     ];
     assert_eq!(code, &expected_value);
 }
@@ -78,13 +77,867 @@ fn test_function_declaration() {
         OpCode::OpDefineGlobal as u8, // OpDefineGlobal to define variable (function in this case)
         0,                            // Position of function name in constant pool.
         OpCode::OpGetGlobal as u8,    // byte OpGetGlobal
-        2,                            // Variable offset in byte_code.
-        OpCode::OpCall as u8,         // OpCall
-        0,                            // argument count for call
-        OpCode::OpPop as u8,          // OpPop
-        OpCode::OpNil as u8,          // OpNil
-        OpCode::OpReturn as u8,       // OpReturn
+        0,                         // Function name is already in constant pool, its slot is reused
+        OpCode::OpCall as u8,      // OpCall
+        0,                         // argument count for call
+        OpCode::OpPop as u8,       // OpPop
+        OpCode::OpReturnNil as u8, // OpReturnNil
+    ];
+
+    assert_eq!(&expected_bytecode, code);
+}
+
+#[test]
+fn test_function_disassembly() {
+    let fun_obj = compile(r#"print "Hamza";"#).unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected_output = "== <script> ==
+0000    1 OpConstant          0 'Hamza'
+0002    | OpPrint
+0003    | OpReturnNil
+";
+    assert_eq!(disassembly, expected_output);
+}
+
+#[test]
+fn test_string_escapes() {
+    let fun_obj = compile(r#"print "tab\tquote\"slash\\line\n";"#).unwrap();
+    let constants = &fun_obj.as_function_ref().chunk.constants;
+    assert_eq!(
+        constants[0],
+        Value::from("tab\tquote\"slash\\line\n".to_owned())
+    );
+}
+
+#[test]
+fn test_string_line_continuation() {
+    let fun_obj = compile("print \"line one \\\nline two\"; print \"a\\\r\nb\\nc\";").unwrap();
+    let constants = &fun_obj.as_function_ref().chunk.constants;
+    assert_eq!(constants[0], Value::from("line one line two".to_owned()));
+    // Works with Windows line endings too, and is different from `\n` escape
+    assert_eq!(constants[1], Value::from("ab\nc".to_owned()));
+}
+
+#[test]
+fn test_unicode_escapes() {
+    let fun_obj = compile(r#"print "\u{1F600} \u{41}";"#).unwrap();
+    let constants = &fun_obj.as_function_ref().chunk.constants;
+    assert_eq!(constants[0], Value::from("😀 A".to_owned()));
+}
+
+#[test]
+fn test_hex_escapes() {
+    let fun_obj = compile(r#"print "\x41"; print "\x7e\x7E\xe9\x00\x410";"#).unwrap();
+    let constants = &fun_obj.as_function_ref().chunk.constants;
+    assert_eq!(constants[0], Value::from("A".to_owned()));
+    // Only two digits are part of the escape, '0' after `\x41` is an ordinary character
+    assert_eq!(constants[1], Value::from("~~é\0A0".to_owned()));
+}
+
+#[test]
+fn test_invalid_unicode_escapes() {
+    let sources = [
+        r#"print "\u{110000}";"#,
+        r#"print "\u{D800}";"#,
+        r#"print "\u1234";"#,
+        r#"print "\u{}";"#,
+        r#"print "\u{12";"#,
+        r#"print "\x4";"#,
+        r#"print "\x4g";"#,
+        r#"print "\x";"#,
+        r#"print "\q";"#,
+    ];
+    for source in sources {
+        let error = compile(source).err().unwrap().to_string();
+        assert!(error.starts_with("[line 1:7] Error"), "{error}");
+    }
+}
+
+#[test]
+fn test_destructuring_declaration() {
+    let fun_obj = compile("{ var (a, b) = f(); }").unwrap();
+    let code = &fun_obj.as_function_ref().chunk.code;
+    let expected_bytecode = vec![
+        OpCode::OpGetGlobal as u8, // Callee
+        0,                         // Position of function name in constant pool
+        OpCode::OpCall as u8,      // Call returns an array of values
+        0,                         // Argument count
+        OpCode::OpUnpack as u8,    // Unpack the array into local slots
+        2,                         // Number of values to unpack
+        OpCode::OpPopN as u8,      // End of scope pops `b` and `a`
+        2,
+        OpCode::OpReturnNil as u8,
+    ];
+
+    assert_eq!(&expected_bytecode, code);
+}
+
+#[test]
+fn test_too_many_return_values() {
+    let values = vec!["1"; 256].join(", ");
+    let error = compile(&format!("fun f() {{ return {values}; }}"))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("Can't return more than 255 values."),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_call_disassembly() {
+    let fun_obj = compile(
+        "
+        fun printHello(){
+            print \"Hello\";
+        }
+
+        printHello();
+    ",
+    )
+    .unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    assert!(
+        disassembly.contains("0006    | OpCall              0\n"),
+        "{disassembly}"
+    );
+    assert!(disassembly.contains("0008    | OpPop\n"), "{disassembly}");
+}
+
+#[test]
+fn test_extend_context_with_owned_source() {
+    let mut context = CompilationContext::new("");
+    for (line, source) in [(1, "var a = 1;"), (2, "a = ;"), (3, "print a;")] {
+        // Source is dropped by the caller right away, context keeps its own copy
+        context.extend_owned(source.to_owned(), line);
+        let result = context.compile();
+        if line == 2 {
+            let error = result.err().unwrap().to_string();
+            assert!(error.starts_with("[line 2:5]"), "{error}");
+        } else {
+            assert!(result.is_ok());
+        }
+    }
+}
+
+#[test]
+fn test_extend_context() {
+    let mut context = CompilationContext::new("");
+
+    context.extend("var a = 1;");
+    assert!(context.compile().is_ok());
+
+    // Failed compilation shouldn't leave partially compiled functions behind
+    context.extend("fun f() { var = ; }");
+    assert!(context.compile().is_err());
+
+    context.extend("var a = 2; print a;");
+    let fun_obj = context.compile().unwrap();
+    let code = &fun_obj.as_function_ref().chunk.code;
+    let expected_bytecode = vec![
+        OpCode::OpConstant as u8,
+        1,
+        OpCode::OpDefineGlobal as u8,
+        0,
+        OpCode::OpGetGlobal as u8,
+        0,
+        OpCode::OpPrint as u8,
+        OpCode::OpReturnNil as u8,
+    ];
+    assert_eq!(&expected_bytecode, code);
+}
+
+#[test]
+fn test_nested_function_disassembly() {
+    let fun_obj = compile(
+        "
+        fun outer() {
+            fun inner() {
+                print 1;
+            }
+            inner();
+        }
+    ",
+    )
+    .unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble_all();
+    let script = disassembly.find("== <script> ==").unwrap();
+    let outer = disassembly.find("== outer ==").unwrap();
+    let inner = disassembly.find("== inner ==").unwrap();
+    assert!(script < outer && outer < inner, "{disassembly}");
+}
+
+#[test]
+fn test_no_effect_warnings() {
+    let mut context = CompilationContext::new(
+        "
+        var x = 1;
+        1 + 2;
+        x;
+        x = 2;
+        clock();
+        -clock();
+    ",
+    );
+    context.set_warnings(true);
+    context.push(CompilerState::new(FunctionType::default_script()));
+    context.compile().unwrap();
+
+    let warnings = context.take_warnings();
+    assert_eq!(
+        warnings,
+        vec![
+            "[line 3:9] Warning at '1': Expression statement has no effect.",
+            "[line 4:9] Warning at 'x': Expression statement has no effect.",
+        ]
+    );
+}
+
+#[test]
+fn test_warnings_disabled_by_default() {
+    let mut context = CompilationContext::new("1 + 2;");
+    context.push(CompilerState::new(FunctionType::default_script()));
+    context.compile().unwrap();
+    assert!(context.take_warnings().is_empty());
+}
+
+#[test]
+fn test_final_expression_without_semicolon() {
+    let fun_obj = compile("1").unwrap();
+    let code = &fun_obj.as_function_ref().chunk.code;
+    let expected_bytecode = vec![
+        OpCode::OpConstant as u8,
+        0,
+        OpCode::OpReturn as u8, // Value of the expression is returned by the script
+        OpCode::OpReturnNil as u8,
+    ];
+    assert_eq!(&expected_bytecode, code);
+
+    // Only top-level expression statements can omit ';'
+    assert!(compile("if (true) 1").is_err());
+    assert!(compile("{ 1 }").is_err());
+    assert!(compile("fun f() { 1 }").is_err());
+    assert!(compile("1 2").is_err());
+}
+
+#[test]
+fn test_end_scope_pops_locals_at_once() {
+    let fun_obj = compile("{ var a; var b; var c; var d; { var e; } }").unwrap();
+    let code = &fun_obj.as_function_ref().chunk.code;
+    let expected_bytecode = vec![
+        OpCode::OpNil as u8, // a
+        OpCode::OpNil as u8, // b
+        OpCode::OpNil as u8, // c
+        OpCode::OpNil as u8, // d
+        OpCode::OpNil as u8, // e
+        OpCode::OpPop as u8, // Single local is popped with `OpPop`, it takes a single byte
+        OpCode::OpPopN as u8,
+        4, // Instead of 4 `OpPop`s
+        OpCode::OpReturnNil as u8,
+    ];
+    assert_eq!(&expected_bytecode, code);
+}
+
+#[test]
+fn test_invalid_assignment_target() {
+    for (source, column) in [("var a; var b;\na + b = 1;", 7), ("\n1 = 2;", 3)] {
+        let error = compile(source).err().unwrap().to_string();
+        assert_eq!(
+            error,
+            format!("[line 2:{column}] Error at '=': Invalid assignment target.\n")
+        );
+    }
+}
+
+#[test]
+fn test_empty_source() {
+    for source in ["", "   \n", "// only a comment"] {
+        let fun_obj = compile(source).unwrap();
+        let code = &fun_obj.as_function_ref().chunk.code;
+        assert_eq!(code, &vec![OpCode::OpReturnNil as u8]);
+    }
+}
+
+#[test]
+fn test_disassemble_to_writer() {
+    use crate::debug::Debug;
+
+    let fun_obj = compile("print 1 + 2;").unwrap();
+    let chunk = &fun_obj.as_function_ref().chunk;
+
+    let mut out = Vec::new();
+    Debug::dissassemble_chunk_to(&mut out, chunk, "test").unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "== test ==
+0000    1 OpConstant          0 '1'
+0002    | OpConstant          1 '2'
+0004    | OpAdd
+0005    | OpPrint
+0006    | OpReturnNil
+"
+    );
+
+    let mut out = Vec::new();
+    let next = Debug::dissassemble_instruction_to(&mut out, chunk, 2).unwrap();
+    assert_eq!(next, 4);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "0002    | OpConstant          1 '2'\n"
+    );
+}
+
+#[test]
+fn test_multiple_var_declarations() {
+    let fun_obj = compile("var x = 1, y = x, z;").unwrap();
+    let code = &fun_obj.as_function_ref().chunk.code;
+    let expected_bytecode = vec![
+        OpCode::OpConstant as u8, // x = 1
+        1,
+        OpCode::OpDefineGlobal as u8,
+        0,
+        OpCode::OpGetGlobal as u8, // y = x, `x` is already defined
+        0,
+        OpCode::OpDefineGlobal as u8,
+        2,
+        OpCode::OpNil as u8, // z
+        OpCode::OpDefineGlobal as u8,
+        3,
+        OpCode::OpReturnNil as u8,
     ];
+    assert_eq!(&expected_bytecode, code);
 
+    let fun_obj = compile("{ var a = 1, b = a; }").unwrap();
+    let code = &fun_obj.as_function_ref().chunk.code;
+    let expected_bytecode = vec![
+        OpCode::OpConstant as u8, // a
+        0,
+        OpCode::OpGetLocal as u8, // b = a
+        0,
+        OpCode::OpPopN as u8,
+        2,
+        OpCode::OpReturnNil as u8,
+    ];
     assert_eq!(&expected_bytecode, code);
 }
+
+#[test]
+fn test_nesting_depth_limit() {
+    // Default limit is generous enough for real code
+    let code = format!("print {}1{};", "(".repeat(100), ")".repeat(100));
+    assert!(compile(&code).is_ok());
+    let code = format!("{}print 1;{}", "{".repeat(100), "}".repeat(100));
+    assert!(compile(&code).is_ok());
+
+    // Adversarial input is an error instead of stack overflow
+    let code = format!("print {}1{};", "(".repeat(100_000), ")".repeat(100_000));
+    let err = compile(&code).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "[line 1:255] Error at '(': Code is nested too deeply.\n"
+    );
+    let code = format!("{}print 1;{}", "{".repeat(100_000), "}".repeat(100_000));
+    let err = compile(&code).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "[line 1:250] Error at '{': Code is nested too deeply.\n"
+    );
+    let code = "fun f() {".repeat(100_000);
+    assert!(compile(&code).is_err());
+    let code = format!("print {}1;", "-".repeat(100_000));
+    assert!(compile(&code).is_err());
+
+    // Limit is configurable
+    let mut context = CompilationContext::new("print ((1));");
+    context.set_max_nesting_depth(3);
+    context.push(CompilerState::new(FunctionType::default_script()));
+    assert!(context.compile().is_err());
+}
+
+#[test]
+fn test_else_if_chain_jumps_to_shared_end() {
+    let fun_obj = compile(
+        "
+        var a = 2;
+        if (a == 1) print 1;
+        else if (a == 2) print 2;
+        else print 3;
+    ",
+    )
+    .unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected = "\
+0000    2 OpConstant          1 '2'
+0002    | OpDefineGlobal      0 'a'
+0004    3 OpGetGlobal         0 'a'
+0006    | OpConstant          2 '1'
+0008    | OpEqual
+0009    | OpPopJumpIfFalse    9 -> 18
+0012    | OpConstant          2 '1'
+0014    | OpPrint
+0015    | OpJump             15 -> 35
+0018    4 OpGetGlobal         0 'a'
+0020    | OpConstant          1 '2'
+0022    | OpEqual
+0023    | OpPopJumpIfFalse   23 -> 32
+0026    | OpConstant          1 '2'
+0028    | OpPrint
+0029    | OpJump             29 -> 35
+0032    5 OpConstant          3 '3'
+0034    | OpPrint
+0035    6 OpReturnNil
+";
+    assert!(disassembly.ends_with(expected), "{disassembly}");
+}
+
+#[test]
+fn test_implicit_return_is_single_byte() {
+    let fun_obj = compile("fun empty() {} fun bare() { return; } fun one() { return 1; }").unwrap();
+    let constants = &fun_obj.as_function_ref().chunk.constants;
+    let code_of = |index: usize| constants[index].as_function_ref().chunk.code.clone();
+
+    // Functions are stored in the constant pool after their names
+    assert_eq!(code_of(1), vec![OpCode::OpReturnNil as u8]);
+    assert_eq!(
+        code_of(3),
+        vec![OpCode::OpReturnNil as u8, OpCode::OpReturnNil as u8]
+    );
+    // Explicit return of a value still uses `OpReturn`
+    assert_eq!(
+        code_of(5),
+        vec![
+            OpCode::OpConstant as u8,
+            0,
+            OpCode::OpReturn as u8,
+            OpCode::OpReturnNil as u8
+        ]
+    );
+}
+
+#[test]
+fn test_return_from_top_level() {
+    for source in [
+        "return 5;",
+        "return;",
+        "{ return 1, 2; }",
+        "if (true) return;",
+    ] {
+        let error = compile(source).err().unwrap().to_string();
+        assert!(
+            error.contains("Error at 'return': Can't return from top-level code."),
+            "{error}"
+        );
+    }
+
+    // Returning from a function nested in a block is fine
+    assert!(compile("{ fun f() { return 5; } }").is_ok());
+}
+
+#[test]
+fn test_repeated_literals_share_constants() {
+    let fun_obj = compile(
+        "
+        var count = 0;
+        count = count + 1;
+        count = count + 1;
+        count = count - 1.0;
+        print \"done\" + \"done\";
+        print -0;
+    ",
+    )
+    .unwrap();
+    let constants = &fun_obj.as_function_ref().chunk.constants;
+    // 'count', 0, 1, 'done' and -0, which is folded into a constant. It isn't shared with 0, their bits differ.
+    assert_eq!(constants.len(), 5, "{constants:?}");
+
+    // Each literal would have its own slot without sharing, it'd exceed 256 constants
+    let source = "var a = 1;\n".repeat(300);
+    let fun_obj = compile(&source).unwrap();
+    assert_eq!(fun_obj.as_function_ref().chunk.constants.len(), 2);
+
+    // Functions aren't shared, even if they look the same
+    let fun_obj = compile("fun f() {} { fun f() {} }").unwrap();
+    let constants = &fun_obj.as_function_ref().chunk.constants;
+    assert_eq!(constants.len(), 3, "{constants:?}");
+}
+
+#[test]
+fn test_while_pops_condition_with_jump() {
+    let fun_obj = compile("{ var i = 0; while (i < 3) i = i + 1; }").unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected = "\
+0000    1 OpConstant          0 '0'
+0002    | OpGetLocal          0
+0004    | OpConstant          1 '3'
+0006    | OpLess
+0007    | OpPopJumpIfFalse    7 -> 21
+0010    | OpGetLocal          0
+0012    | OpConstant          2 '1'
+0014    | OpAdd
+0015    | OpSetLocal          0
+0017    | OpPop
+0018    | OpLoop             18 -> 2
+0021    | OpPop
+0022    | OpReturnNil
+";
+    assert!(disassembly.ends_with(expected), "{disassembly}");
+}
+
+#[test]
+fn test_grouping_depth_boundary() {
+    let compile_with_limit = |code: &str, limit: usize| {
+        let mut context = CompilationContext::new(code);
+        context.set_max_nesting_depth(limit);
+        context.push(CompilerState::new(FunctionType::default_script()));
+        context.compile()
+    };
+    // Statement takes one level, and every expression, including the one in parenthesis, takes one more
+    let nested = |depth: usize| format!("print {}1{};", "(".repeat(depth), ")".repeat(depth));
+
+    assert!(compile_with_limit(&nested(18), 20).is_ok());
+    let error = compile_with_limit(&nested(19), 20)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("Code is nested too deeply."), "{error}");
+
+    // Depth is reset after an error, so next source of a REPL isn't affected
+    let (too_deep, fine) = (nested(1000), nested(18));
+    let mut context = CompilationContext::new("");
+    context.set_max_nesting_depth(20);
+    context.extend(&too_deep);
+    assert!(context.compile().is_err());
+    context.extend(&fine);
+    assert!(context.compile().is_ok());
+}
+
+#[test]
+fn test_repeat_until_loops_while_condition_is_falsey() {
+    let fun_obj = compile("{ var i = 0; repeat i = i + 1; until (i >= 3); }").unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected = "\
+0000    1 OpConstant          0 '0'
+0002    | OpGetLocal          0
+0004    | OpConstant          1 '1'
+0006    | OpAdd
+0007    | OpSetLocal          0
+0009    | OpPop
+0010    | OpGetLocal          0
+0012    | OpConstant          2 '3'
+0014    | OpGreaterEqual
+0015    | OpPopJumpIfFalse   15 -> 21
+0018    | OpJump             18 -> 24
+0021    | OpLoop             21 -> 2
+0024    | OpPop
+0025    | OpReturnNil
+";
+    assert!(disassembly.ends_with(expected), "{disassembly}");
+
+    let error = compile("repeat {} while (true);")
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("Expect 'until' after 'repeat' body."),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_tail_calls_are_only_whole_return_operands() {
+    let tail_calls = |code: &str| {
+        let mut context = CompilationContext::new(code);
+        context.push(CompilerState::new(FunctionType::default_script()));
+        context.compile().unwrap();
+        context.take_tail_calls()
+    };
+
+    let calls = tail_calls("fun f(x) { return f(x); }");
+    assert_eq!(
+        calls,
+        [TailCall {
+            function: Some("f".to_owned()),
+            // `OpGetGlobal f`, `OpGetLocal x`
+            offset: 4,
+        }]
+    );
+    // Only the outer call of chained calls, whose result is returned
+    assert_eq!(tail_calls("fun f(x) { return f(x)(x); }").len(), 1);
+    assert_eq!(tail_calls("fun f(x) { return f(f(x)); }").len(), 1);
+    assert_eq!(
+        tail_calls("fun f(x) { if (x) return f(x); else return f(1); }").len(),
+        2
+    );
+
+    for code in [
+        "fun f(x) { return f(x) + 1; }",
+        "fun f(x) { return 1 + f(x); }",
+        "fun f(x) { return -f(x); }",
+        "fun f(x) { return (f(x)); }",
+        "fun f(x) { return x and f(x); }",
+        "fun f(x) { return f(x), f(x); }",
+        "fun f(x) { return x = f(x); }",
+        "fun f(x) { f(x); return x; }",
+        "fun f(x) { var y = f(x); return y; }",
+        "f(1);",
+    ] {
+        assert!(tail_calls(code).is_empty(), "{code}");
+    }
+
+    // Nested function's `return` doesn't affect calls of the enclosing one
+    let calls = tail_calls("fun f(x) { fun g() { return f(1); } g(); return x; }");
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].function.as_deref(), Some("g"));
+}
+
+#[test]
+fn test_negative_number_literals_are_folded() {
+    let fun_obj = compile("var a = -5;").unwrap();
+    let chunk = &fun_obj.as_function_ref().chunk;
+    assert_eq!(
+        chunk.code,
+        [
+            OpCode::OpConstant as u8,
+            1,
+            OpCode::OpDefineGlobal as u8,
+            0,
+            OpCode::OpReturnNil as u8
+        ]
+    );
+    assert_eq!(chunk.constants[1], Value::from(-5.0));
+
+    // Variables and other expressions are negated at runtime, and only the innermost minus of `- -5` is folded
+    for (code, negations) in [
+        ("var a = 1; var b = -a;", 1),
+        ("var b = -(5);", 1),
+        ("var b = - -5;", 1),
+        ("var b = -5 - -2.5;", 0),
+        ("var b = -\"a\";", 1),
+    ] {
+        let fun_obj = compile(code).unwrap();
+        let disassembly = fun_obj.as_function_ref().disassemble();
+        let count = disassembly.matches("OpNegate").count();
+        assert_eq!(count, negations, "{code}");
+    }
+
+    // Literal compiled after a failed negation isn't negated, like the next line of a REPL. Compilation may fail right
+    // before or right after the minus in front of the literal, so every depth around the limit is tried.
+    let sources: Vec<_> = (15..25)
+        .map(|minuses| format!("print {}5;", "-".repeat(minuses)))
+        .collect();
+    let mut context = CompilationContext::new("");
+    context.set_max_nesting_depth(20);
+    for source in &sources {
+        context.extend(source);
+        if context.compile().is_ok() {
+            continue;
+        }
+        context.extend("print 7;");
+        let fun_obj = context.compile().unwrap();
+        assert_eq!(
+            fun_obj.as_function_ref().chunk.constants[0],
+            Value::from(7.0),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_underscore_can_be_redeclared_but_not_read() {
+    assert!(compile("{ var _ = 1; var _ = 2; }").is_ok());
+    assert!(compile("fun size() { return 1, 2, 3; } { var (a, _, _) = size(); print a; }").is_ok());
+    assert!(compile("fun f(_, _) {}").is_ok());
+    // Assigning is fine, it discards the value too
+    assert!(compile("{ var _ = 1; _ = 2; }").is_ok());
+    // Other names still can't be redeclared
+    let error = compile("{ var a = 1; var a = 2; }")
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("Already a variable with this name in this scope."),
+        "{error}"
+    );
+
+    for code in ["{ var _ = 1; print _; }", "var _ = 1; print _ + 1;"] {
+        let error = compile(code).err().unwrap().to_string();
+        assert!(
+            error.contains("Can't read '_', it only discards values."),
+            "{code}: {error}"
+        );
+    }
+}
+
+#[test]
+fn test_indexing_compiles_to_get_index() {
+    let fun_obj = compile("var a = [1]; a[-1];").unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected = "\
+0006    | OpGetGlobal         0 'a'
+0008    | OpConstant          2 '-1'
+0010    | OpGetIndex
+0011    | OpPop
+";
+    assert!(disassembly.contains(expected), "{disassembly}");
+
+    let error = compile("var a = [1]; a[0;").err().unwrap().to_string();
+    assert!(error.contains("Expected ']' after index."), "{error}");
+}
+
+#[test]
+fn test_array_literal_builds_array_at_once() {
+    let fun_obj = compile("[1, 2, 3];").unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected = "\
+0000    1 OpConstant          0 '1'
+0002    | OpConstant          1 '2'
+0004    | OpConstant          2 '3'
+0006    | OpBuildArray        3
+0008    | OpPop
+";
+    assert!(disassembly.contains(expected), "{disassembly}");
+
+    let fun_obj = compile("[];").unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    assert!(
+        disassembly.contains("OpBuildArray        0"),
+        "{disassembly}"
+    );
+
+    let elements = vec!["1"; 256].join(", ");
+    let error = compile(&format!("[{elements}];"))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("Can't have more than 255 elements in an array literal."),
+        "{error}"
+    );
+    let error = compile("[1, 2;").err().unwrap().to_string();
+    assert!(
+        error.contains("Expected ']' after array elements."),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_global_statement_skips_locals() {
+    let fun_obj = compile("var x; { var x = 1; global x = x; }").unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected = "\
+0003    | OpConstant          1 '1'
+0005    | OpGetLocal          0
+0007    | OpSetGlobal         0 'x'
+0009    | OpPop
+";
+    assert!(disassembly.contains(expected), "{disassembly}");
+
+    for (code, message) in [
+        ("global = 1;", "Expected variable name after 'global'."),
+        ("global x;", "Expected '=' after global variable name."),
+        ("global x = 1", "Expected ';' after value."),
+        ("var y = global x = 1;", "Expected expression"),
+    ] {
+        let error = compile(code).err().unwrap().to_string();
+        assert!(error.contains(message), "{code}: {error}");
+    }
+}
+
+#[test]
+fn test_chained_comparisons_keep_middle_operand() {
+    let fun_obj = compile("1 < 2 <= 3;").unwrap();
+    let disassembly = fun_obj.as_function_ref().disassemble();
+    let expected = "\
+0000    1 OpConstant          0 '1'
+0002    | OpConstant          1 '2'
+0004    | OpTuck
+0005    | OpLess
+0006    | OpPopJumpIfFalse    6 -> 15
+0009    | OpConstant          2 '3'
+0011    | OpLessEqual
+0012    | OpJump             12 -> 17
+0015    | OpPop
+0016    | OpFalse
+0017    | OpPop
+";
+    assert!(disassembly.contains(expected), "{disassembly}");
+
+    // Single comparison and equality aren't affected
+    let disassembly = compile("1 < 2 == true;")
+        .unwrap()
+        .as_function_ref()
+        .disassemble();
+    assert!(!disassembly.contains("OpTuck"), "{disassembly}");
+}
+
+#[test]
+fn test_function_declarations_and_expressions_compile_alike() {
+    let body = "(a, b) { return a + b; }";
+    let declared = compile(&format!("fun add{body}")).unwrap();
+    let expression = compile(&format!("var add = fun add{body};")).unwrap();
+    let anonymous = compile(&format!("var add = fun {body};")).unwrap();
+
+    let function = |script: &Value| {
+        let constants = &script.as_function_ref().chunk.constants;
+        let function = constants.iter().find(|value| value.is_function()).unwrap();
+        let function = function.as_function_ref();
+        (
+            function.arity,
+            function.chunk.code.clone(),
+            function.name.clone(),
+        )
+    };
+    let (arity, code, name) = function(&declared);
+    assert_eq!(arity, 2);
+    assert_eq!(name.as_deref(), Some("add"));
+    assert_eq!(function(&expression), (arity, code.clone(), name));
+    // Anonymous function only differs by not having a name
+    assert_eq!(function(&anonymous), (arity, code, None));
+
+    let disassembly = anonymous.as_function_ref().disassemble_all();
+    assert!(
+        disassembly.contains("== <anonymous fn> =="),
+        "{disassembly}"
+    );
+
+    // At the start of a statement, `fun` is always a declaration
+    let error = compile("fun (a) {}").err().unwrap().to_string();
+    assert!(error.contains("Expected function name"), "{error}");
+}
+
+#[test]
+fn test_annotated_parameters_are_checked_at_entry() {
+    let script = compile("fun f(a, b: number, c) { return a; }").unwrap();
+    let disassembly = script.as_function_ref().disassemble_all();
+    let expected = "\
+== f ==
+0000    1 OpCheckType         1 'number'
+0003    | OpGetLocal          0
+";
+    assert!(disassembly.contains(expected), "{disassembly}");
+    assert_eq!(
+        disassembly.matches("OpCheckType").count(),
+        1,
+        "{disassembly}"
+    );
+
+    // Unannotated parameters emit nothing
+    let disassembly = compile("fun f(a, b) {}")
+        .unwrap()
+        .as_function_ref()
+        .disassemble_all();
+    assert!(!disassembly.contains("OpCheckType"), "{disassembly}");
+
+    for (code, message) in [
+        (
+            "fun f(x: int) {}",
+            "Unknown type 'int', expected one of: bool, number, string, function, array.",
+        ),
+        ("fun f(x:) {}", "Expected type name after ':'."),
+        ("fun f(x: nil) {}", "Expected type name after ':'."),
+    ] {
+        let error = compile(code).err().unwrap().to_string();
+        assert!(error.contains(message), "{code}: {error}");
+    }
+}
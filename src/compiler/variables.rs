@@ -37,6 +37,11 @@ impl CompilationContext<'_> {
 
         let name = self.parser.previous.clone().ok_or(error)?;
 
+        // `_` only discards values, it can't be read, so there's no confusion about which of them would be read
+        if self.is_discard(&name) {
+            return self.add_local_variable(name);
+        }
+
         for local in self.compiler().locals.iter().rev() {
             let scope_depth = self.compiler().scope_depth;
             // let local = &self.compiler().locals[i as usize];
@@ -55,11 +60,16 @@ impl CompilationContext<'_> {
         Ok(())
     }
 
+    /// Returns true if the variable is `_`, which can be declared more than once in a scope, but can't be read
+    fn is_discard(&self, name: &Token) -> bool {
+        name.lexeme(self.source) == "_"
+    }
+
     fn are_identifiers_equal(&self, token_a: &Token, token_b: &Token) -> bool {
         if token_a.length != token_b.length {
             return false;
         }
-        token_a.as_str(self.source) == token_b.as_str(self.source)
+        token_a.lexeme(self.source) == token_b.lexeme(self.source)
     }
 
     fn resolve_local(&mut self, name: &Token) -> Result<i32, CompilerError> {
@@ -104,6 +114,16 @@ impl CompilationContext<'_> {
         local.depth = scope_depth;
     }
 
+    /// Marks last `count` locals as initialized, when multiple variables are declared by one initializer
+    pub(super) fn mark_last_initialized(&mut self, count: usize) {
+        let scope_depth = self.compiler().scope_depth;
+        let locals = &mut self.compiler_mut().locals;
+        let start = locals.len() - count;
+        for local in &mut locals[start..] {
+            local.depth = scope_depth;
+        }
+    }
+
     /// Writes bytecode to define variable
     pub(super) fn define_variable(&mut self, global: u8) -> Result<(), CompilerError> {
         if self.compiler().scope_depth > 0 {
@@ -143,8 +163,11 @@ impl CompilationContext<'_> {
         if can_assign && self.match_curr_ty(TokenType::Equal)? {
             // Current variable can assign, and current token is `Equal`, evaluate the expression on the right
             self.expression()?;
+            self.has_side_effect = true;
             // Emit the OpCode to set global variable, alongside the variable name index.
             self.emit_bytes(set_opcode as u8, variable_offset)
+        } else if self.is_discard(name) {
+            Err(self.construct_token_error(false, "Can't read '_', it only discards values."))
         } else {
             // Can't assign, or current token is not `Equal`, parse it as reading the global variable
             self.emit_bytes(get_opcode as u8, variable_offset)
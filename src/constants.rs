@@ -1,3 +1,22 @@
 pub const UINT8_COUNT: usize = (u8::MAX as usize) + 1;
 pub const FRAMES_MAX: usize = 64;
 pub const STACK_MAX: usize = FRAMES_MAX * UINT8_COUNT;
+/// Default limit of how deep statements and expressions can be nested in each other while compiling
+pub const MAX_NESTING_DEPTH: usize = 250;
+
+/// Exit codes of the interpreter when a script fails, following the Unix sysexits convention
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Source code has syntax errors, or can't be scanned (`EX_DATAERR`)
+    CompileError = 65,
+    /// Script failed while running (`EX_SOFTWARE`)
+    RuntimeError = 70,
+    /// Source code couldn't be read (`EX_IOERR`)
+    IoError = 74,
+}
+
+impl From<ExitCode> for i32 {
+    fn from(code: ExitCode) -> Self {
+        code as Self
+    }
+}
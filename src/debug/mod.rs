@@ -1,5 +1,13 @@
-/// Debug module to print instructions in debug_trace_execution mode
-use crate::chunk::{Chunk, OpCode};
+/// Debug module to print instructions in debug_trace_execution mode, or to disassemble bytecode for tooling
+use std::{
+    fmt::Write as _, // import without risk of name clashing
+    io,
+};
+
+use crate::{
+    chunk::{Chunk, OpCode},
+    scanner::token::Token,
+};
 
 /// Struct doesn't have any properties
 pub struct Debug;
@@ -7,28 +15,81 @@ pub struct Debug;
 impl Debug {
     /// Receives a chunk, and a name for that chunk, and print instructions
     pub fn dissassemble_chunk(chunk: &Chunk, name: &str) {
-        println!("== {name} ==");
+        // Nothing can be done if stdout is closed
+        let _ = Debug::dissassemble_chunk_to(&mut io::stdout(), chunk, name);
+    }
+
+    /// Same as `dissassemble_chunk`, but writes instructions to `out`, e.g. a file or a buffer
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails
+    pub fn dissassemble_chunk_to(
+        out: &mut dyn io::Write,
+        chunk: &Chunk,
+        name: &str,
+    ) -> io::Result<()> {
+        out.write_all(Debug::chunk_to_string(chunk, name).as_bytes())
+    }
+
+    /// Same as `dissassemble_chunk`, but returns instructions as a string instead of printing them
+    #[must_use]
+    pub fn chunk_to_string(chunk: &Chunk, name: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "== {name} ==");
 
         // Starting from 0 offset
         let mut offset = 0;
 
-        // if offset is less than byte code length, print instruction and update the offset
+        // if offset is less than byte code length, write instruction and update the offset
         while offset < chunk.code.len() {
-            offset = Debug::dissassemble_instruction(chunk, offset);
+            offset = Debug::write_instruction(&mut out, chunk, offset);
         }
+
+        out
+    }
+
+    /// Returns line, type and lexeme of a token on a single line, used to dump tokens produced by the scanner
+    #[must_use]
+    pub fn token_to_string(token: &Token, source: &str) -> String {
+        // Padding is ignored when `Debug` output of enum is written directly
+        let ty = format!("{:?}", token.ty);
+        format!("{: >4} {ty: <12} '{}'", token.line, token.lexeme(source))
     }
 
     // Print the current instruction and returns new offset
     pub fn dissassemble_instruction(chunk: &Chunk, offset: usize) -> usize {
-        print!("{:04} ", offset);
+        let mut out = String::new();
+        let offset = Debug::write_instruction(&mut out, chunk, offset);
+        print!("{out}");
+        offset
+    }
+
+    /// Same as `dissassemble_instruction`, but writes the instruction to `out`. Returns offset of the next instruction.
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails
+    pub fn dissassemble_instruction_to(
+        out: &mut dyn io::Write,
+        chunk: &Chunk,
+        offset: usize,
+    ) -> io::Result<usize> {
+        let mut instruction = String::new();
+        let offset = Debug::write_instruction(&mut instruction, chunk, offset);
+        out.write_all(instruction.as_bytes())?;
+        Ok(offset)
+    }
+
+    // Writes the current instruction to `out` and returns new offset
+    fn write_instruction(out: &mut String, chunk: &Chunk, offset: usize) -> usize {
+        let _ = write!(out, "{:04} ", offset);
 
         // If offset is greater than 0, i.e. at least one byte has been processed before
         // and previous byte and this byte is on the same line, just print a '|'
         if offset > 0 && chunk.lines[offset] == chunk.lines[offset - 1] {
-            print!("   | ");
+            out.push_str("   | ");
         } else {
             // print line number
-            print!("{: >4} ", chunk.lines[offset]);
+            let _ = write!(out, "{: >4} ", chunk.lines[offset]);
         }
 
         // First byte of code is consumed here.
@@ -36,33 +97,58 @@ impl Debug {
         // Convert u8 to OpCode
         if let Ok(opcode) = OpCode::try_from(instruction) {
             match opcode {
-                OpCode::OpReturn => Debug::simple_instruction("OpReturn", offset),
-                OpCode::OpConstant => Debug::constant_instruction("OpConstant", chunk, offset),
-                OpCode::OpNegate => Debug::simple_instruction("OpNegate", offset),
-                OpCode::OpAdd => Debug::simple_instruction("OpAdd", offset),
-                OpCode::OpSubtract => Debug::simple_instruction("OpSubtract", offset),
-                OpCode::OpMultiply => Debug::simple_instruction("OpMultiply", offset),
-                OpCode::OpDivide => Debug::simple_instruction("OpDivide", offset),
-                OpCode::OpNil => Debug::simple_instruction("OpNil", offset),
-                OpCode::OpTrue => Debug::simple_instruction("OpTrue", offset),
-                OpCode::OpFalse => Debug::simple_instruction("OpFalse", offset),
-                OpCode::OpNot => Debug::simple_instruction("OpNot", offset),
-                OpCode::OpEqual => Debug::simple_instruction("OpEqual", offset),
-                OpCode::OpGreater => Debug::simple_instruction("OpGreater", offset),
-                OpCode::OpLess => Debug::simple_instruction("OpLess", offset),
-                OpCode::OpPrint => Debug::simple_instruction("OpPrint", offset),
-                OpCode::OpPop => Debug::simple_instruction("OpPop", offset),
+                OpCode::OpReturn => Debug::simple_instruction(out, "OpReturn", offset),
+                OpCode::OpReturnNil => Debug::simple_instruction(out, "OpReturnNil", offset),
+                OpCode::OpConstant => Debug::constant_instruction(out, "OpConstant", chunk, offset),
+                OpCode::OpNegate => Debug::simple_instruction(out, "OpNegate", offset),
+                OpCode::OpAdd => Debug::simple_instruction(out, "OpAdd", offset),
+                OpCode::OpSubtract => Debug::simple_instruction(out, "OpSubtract", offset),
+                OpCode::OpMultiply => Debug::simple_instruction(out, "OpMultiply", offset),
+                OpCode::OpDivide => Debug::simple_instruction(out, "OpDivide", offset),
+                OpCode::OpNil => Debug::simple_instruction(out, "OpNil", offset),
+                OpCode::OpTrue => Debug::simple_instruction(out, "OpTrue", offset),
+                OpCode::OpFalse => Debug::simple_instruction(out, "OpFalse", offset),
+                OpCode::OpNot => Debug::simple_instruction(out, "OpNot", offset),
+                OpCode::OpEqual => Debug::simple_instruction(out, "OpEqual", offset),
+                OpCode::OpGreater => Debug::simple_instruction(out, "OpGreater", offset),
+                OpCode::OpLess => Debug::simple_instruction(out, "OpLess", offset),
+                OpCode::OpGreaterEqual => Debug::simple_instruction(out, "OpGreaterEqual", offset),
+                OpCode::OpLessEqual => Debug::simple_instruction(out, "OpLessEqual", offset),
+                OpCode::OpPrint => Debug::simple_instruction(out, "OpPrint", offset),
+                OpCode::OpPop => Debug::simple_instruction(out, "OpPop", offset),
+                OpCode::OpDup => Debug::simple_instruction(out, "OpDup", offset),
+                OpCode::OpTuck => Debug::simple_instruction(out, "OpTuck", offset),
+                OpCode::OpGetIndex => Debug::simple_instruction(out, "OpGetIndex", offset),
+                OpCode::OpPopN => Debug::byte_instruction(out, "OpPopN", chunk, offset),
                 OpCode::OpDefineGlobal => {
-                    Debug::constant_instruction("OpDefineGlobal", chunk, offset)
+                    Debug::constant_instruction(out, "OpDefineGlobal", chunk, offset)
                 }
-                OpCode::OpGetGlobal => Debug::constant_instruction("OpGetGlobal", chunk, offset),
-                OpCode::OpSetGlobal => Debug::constant_instruction("OpSetGlobal", chunk, offset),
-                OpCode::OpGetLocal => Debug::byte_instruction("OpGetLocal", chunk, offset),
-                OpCode::OpSetLocal => Debug::byte_instruction("OpSetLocal", chunk, offset),
-                OpCode::OpJump => Debug::jump_instruction("OpJump", 1, chunk, offset),
-                OpCode::OpJumpIfFalse => Debug::jump_instruction("OpJumpIfFalse", 1, chunk, offset),
-                OpCode::OpLoop => Debug::jump_instruction("OpLoop", -1, chunk, offset),
-                OpCode::OpCall => Debug::byte_instruction("OpCall", chunk, offset),
+                OpCode::OpGetGlobal => {
+                    Debug::constant_instruction(out, "OpGetGlobal", chunk, offset)
+                }
+                OpCode::OpSetGlobal => {
+                    Debug::constant_instruction(out, "OpSetGlobal", chunk, offset)
+                }
+                OpCode::OpGetProperty => {
+                    Debug::constant_instruction(out, "OpGetProperty", chunk, offset)
+                }
+                OpCode::OpGetLocal => Debug::byte_instruction(out, "OpGetLocal", chunk, offset),
+                OpCode::OpSetLocal => Debug::byte_instruction(out, "OpSetLocal", chunk, offset),
+                OpCode::OpJump => Debug::jump_instruction(out, "OpJump", 1, chunk, offset),
+                OpCode::OpJumpIfFalse => {
+                    Debug::jump_instruction(out, "OpJumpIfFalse", 1, chunk, offset)
+                }
+                OpCode::OpPopJumpIfFalse => {
+                    Debug::jump_instruction(out, "OpPopJumpIfFalse", 1, chunk, offset)
+                }
+                OpCode::OpJumpIfNotNil => {
+                    Debug::jump_instruction(out, "OpJumpIfNotNil", 1, chunk, offset)
+                }
+                OpCode::OpLoop => Debug::jump_instruction(out, "OpLoop", -1, chunk, offset),
+                OpCode::OpCall => Debug::byte_instruction(out, "OpCall", chunk, offset),
+                OpCode::OpCheckType => Debug::check_type_instruction(out, chunk, offset),
+                OpCode::OpBuildArray => Debug::byte_instruction(out, "OpBuildArray", chunk, offset),
+                OpCode::OpUnpack => Debug::byte_instruction(out, "OpUnpack", chunk, offset),
             }
         } else {
             // Print invalid instruction error
@@ -73,33 +159,52 @@ impl Debug {
     }
 
     /// Print the constant instruction and returns new offset
-    fn constant_instruction(name: &str, chunk: &Chunk, offset: usize) -> usize {
+    fn constant_instruction(out: &mut String, name: &str, chunk: &Chunk, offset: usize) -> usize {
         // First byte has been consumed, which is OpCode. Second byte will be the offset of the constant
         let constant_index = chunk.code[offset + 1];
         // Print the name of the instruction, and offset of the constant
-        print!("{: <16} {: >4} '", name, constant_index);
+        let _ = write!(out, "{: <16} {: >4} '", name, constant_index);
         // Print the actuall constant value
-        println!("{}'", chunk.constants[constant_index as usize]);
+        let _ = writeln!(out, "{}'", chunk.constants[constant_index as usize]);
         // Constant instruction/opcode has 2 bytes, consumed both bytes so new offset would be offset + 2
         offset + 2
     }
 
     // Prints simple instruction and returns new offset
-    fn simple_instruction(name: &str, offset: usize) -> usize {
-        println!("{name}");
+    fn simple_instruction(out: &mut String, name: &str, offset: usize) -> usize {
+        let _ = writeln!(out, "{name}");
         // Since simple instruction is one byte, new offset would be offset + 1
         offset + 1
     }
 
-    fn byte_instruction(name: &str, chunk: &Chunk, offset: usize) -> usize {
+    fn byte_instruction(out: &mut String, name: &str, chunk: &Chunk, offset: usize) -> usize {
         let slot = chunk.code[offset + 1];
-        println!("{: <16} {: >4}", name, slot);
+        let _ = writeln!(out, "{: <16} {: >4}", name, slot);
         offset + 2
     }
 
-    fn jump_instruction(name: &str, sign: isize, chunk: &Chunk, offset: usize) -> usize {
+    /// Prints the slot of the parameter and its expected type
+    fn check_type_instruction(out: &mut String, chunk: &Chunk, offset: usize) -> usize {
+        let slot = chunk.code[offset + 1];
+        let constant_index = chunk.code[offset + 2];
+        let _ = writeln!(
+            out,
+            "{: <16} {: >4} '{}'",
+            "OpCheckType", slot, chunk.constants[constant_index as usize]
+        );
+        offset + 3
+    }
+
+    fn jump_instruction(
+        out: &mut String,
+        name: &str,
+        sign: isize,
+        chunk: &Chunk,
+        offset: usize,
+    ) -> usize {
         let jump = u16::from_be_bytes([chunk.code[offset + 1], chunk.code[offset + 2]]);
-        println!(
+        let _ = writeln!(
+            out,
             "{: <16} {: >4} -> {}",
             name,
             offset,
@@ -1,7 +1,13 @@
-use std::{fs, process};
+use std::{fs, path::Path};
 
 use crate::{
-    compiler::{CompilationContext, CompilerState, types::FunctionType},
+    compiler::{
+        CompilationContext, CompilerState, errors::CompilerError, parser::ParserError,
+        types::FunctionType,
+    },
+    debug::Debug,
+    scanner::Scanner,
+    value::{Value, objects::FunctionObject},
     vm::{VM, errors::VMError},
 };
 
@@ -9,59 +15,139 @@ pub mod chunk;
 pub mod cli;
 pub mod compiler;
 pub mod constants;
-#[cfg(feature = "debug_trace_execution")]
 pub mod debug;
 pub mod scanner;
+#[cfg(test)]
+mod test_utils;
 pub mod tests;
 pub mod value;
 pub mod vm;
 
-// Helper function which just logs if any errors are returned
-fn execute(code: &str, vm: &mut VM) {
-    if let Err(e) = interpret(code, vm) {
-        vm.reset_vm();
-        match e {
-            VMError::CompileError(e) => {
-                eprintln!("Compiler Error: {e}");
-                process::exit(65);
-            }
-            VMError::RuntimeError(e) => {
-                eprintln!("Runtime Error: {e}");
-                process::exit(70);
-            }
-        }
-    }
-}
-
 /// A separate function which returns errors. Can be helpfull when writing tests to test against certain types of errors
 ///
 /// # Errors
 ///
 /// Returns a `VMError` if compilation or execution gone wrong
-pub fn interpret(code: &str, vm: &mut VM) -> Result<(), VMError> {
+pub fn interpret(code: &str, vm: &mut VM) -> Result<Value, VMError> {
+    interpret_with_warnings(code, vm, false, None)
+}
+
+// Same as `interpret`, but optionally prints compiler warnings to stderr. Imports are resolved relative to `file_path`, if code is read from a file.
+fn interpret_with_warnings(
+    code: &str,
+    vm: &mut VM,
+    warnings: bool,
+    file_path: Option<&Path>,
+) -> Result<Value, VMError> {
     let mut context = CompilationContext::new(code);
+    context.set_warnings(warnings);
+    if let Some(file_path) = file_path {
+        context.set_file_path(file_path);
+    }
 
     let function_type = FunctionType::default_script();
     context.push(CompilerState::new(function_type));
-    let top_function = context.compile().map_err(VMError::CompileError)?;
+    let top_function = context.compile();
+    for warning in context.take_warnings() {
+        eprintln!("{warning}");
+    }
+    let top_function = top_function.map_err(VMError::CompileError)?;
 
-    // Value on stack should be garbage collected
-    let stack_value = top_function.clone();
-    vm.push(stack_value);
+    let result = vm.run_isolated(top_function.clone());
+    vm.free_script(top_function);
+    result
+}
 
-    vm.call(top_function, 0)?;
-    vm.interpret()
+/// Compiles code into a top-level function, without running it. Function can be run later, or more than once,
+/// by converting it into a `Value` and passing it to `VM::run_isolated`.
+///
+/// Converted `Value` isn't owned by any `VM`, caller frees it with `VM::free_script` once it won't be run anymore.
+///
+/// Imports are resolved relative to the current directory.
+///
+/// # Errors
+///
+/// Returns a `CompilerError` if code has syntax errors
+pub fn compile(source: &str) -> Result<FunctionObject, CompilerError> {
+    let mut context = CompilationContext::new(source);
+    context.push(CompilerState::new(FunctionType::default_script()));
+    context.compile_script()
 }
 
-/// Executes code from a file
-pub fn run_file(file_path: &str) {
+/// Compiles and executes code on a fresh `VM`, and returns the value returned by the top-level script.
+/// Errors are returned as values, it never exits the process, so it's safe to call from a host program.
+///
+/// Runtime objects are owned by the `VM`, which gets cleaned up before returning. So an object result is copied into a literal string, which is how it would be printed.
+///
+/// # Errors
+///
+/// Returns a `VMError` if compilation or execution gone wrong
+pub fn eval(source: &str) -> Result<Value, VMError> {
     let mut vm = VM::new();
-    // Reads file and returns Result. If result is Ok, execute the string obtained from file
-    if let Ok(content) = fs::read_to_string(file_path) {
-        execute(&content, &mut vm);
-        vm.reset_vm();
-    } else {
-        eprintln!("Can't read code from file: {file_path}");
-        process::exit(74);
+    let result = interpret(source, &mut vm).map(|value| {
+        if value.is_object() {
+            // Pointer will be dangling after `reset_vm`, copy the value out
+            Value::from(value.to_string())
+        } else {
+            value
+        }
+    });
+    vm.reset_vm();
+    result
+}
+
+/// Executes code from a file. Compiler warnings are printed if `warnings` is true.
+/// If `integer_division` is true, `/` floors the result when both operands are whole numbers.
+///
+/// Process is never exited from here, it's up to the caller, like `main`, to report the error and pick an exit code.
+/// # Errors
+///
+/// Returns `VMError::IoError` if file can't be read, or errors of compiling and running it. `VMError::Exit` is returned
+/// if the script called `exit()`, even with code 0.
+pub fn run_file(file_path: &str, warnings: bool, integer_division: bool) -> Result<(), VMError> {
+    let content = read_source(file_path)?;
+
+    let mut vm = VM::new();
+    vm.integer_division = integer_division;
+    let result = interpret_with_warnings(&content, &mut vm, warnings, Some(Path::new(file_path)));
+    // Objects are freed whether the script succeeded or not
+    vm.reset_vm();
+    result.map(|_| ())
+}
+
+/// Reads source code of a file, like a script to run
+fn read_source(file_path: &str) -> Result<String, VMError> {
+    fs::read_to_string(file_path)
+        .map_err(|e| VMError::IoError(format!("Can't read code from file {file_path}: {e}")))
+}
+
+/// Prints every token scanned from a file, without compiling or executing it. Scanning stops at the first invalid token.
+/// # Errors
+///
+/// Returns `VMError::IoError` if file can't be read, and `VMError::CompileError` if an invalid token is found
+pub fn dump_tokens(file_path: &str) -> Result<(), VMError> {
+    let content = read_source(file_path)?;
+
+    for result in Scanner::new(&content) {
+        let token = result.map_err(|e| {
+            VMError::CompileError(CompilerError::ParserError(ParserError::ScannerError(e)))
+        })?;
+        println!("{}", Debug::token_to_string(&token, &content));
     }
+    Ok(())
+}
+
+/// Compiles a file and prints bytecode of every function in it, without executing it
+/// # Errors
+///
+/// Returns `VMError::IoError` if file can't be read, and `VMError::CompileError` if it doesn't compile
+pub fn disassemble_file(file_path: &str) -> Result<(), VMError> {
+    let content = read_source(file_path)?;
+
+    let mut context = CompilationContext::new(&content);
+    context.set_file_path(Path::new(file_path));
+    context.push(CompilerState::new(FunctionType::default_script()));
+    let top_function = context.compile().map_err(VMError::CompileError)?;
+    print!("{}", top_function.as_function_ref().disassemble_all());
+    Ok(())
 }
@@ -1,15 +1,37 @@
+use std::process;
+
 use clap::Parser;
 use rslox::{
     cli::{Cli, repl},
-    run_file,
+    disassemble_file, dump_tokens, run_file,
+    vm::errors::VMError,
 };
 
 fn main() {
     let cli = Cli::parse();
 
-    if let Some(file_path) = cli.file {
-        run_file(&file_path);
+    let result = if let Some(file_path) = &cli.dump_tokens {
+        dump_tokens(file_path)
+    } else if let Some(file_path) = &cli.disassemble {
+        disassemble_file(file_path)
+    } else if let Some(file_path) = &cli.file {
+        run_file(file_path, cli.warnings, cli.integer_division)
     } else {
-        repl();
+        repl(&cli)
+    };
+    if let Err(e) = result {
+        exit_with_error(e);
+    }
+}
+
+/// Reports the error of running a file and exits with a code following sysexits convention, see `ExitCode`
+fn exit_with_error(error: VMError) -> ! {
+    match &error {
+        VMError::CompileError(e) => eprintln!("Compiler Error: {e}"),
+        VMError::RuntimeError(e) => eprintln!("Runtime Error: {e}"),
+        VMError::IoError(e) => eprintln!("{e}"),
+        // Script asked to exit, VM has already been cleaned up
+        VMError::Exit(_) => {}
     }
+    process::exit(error.exit_code());
 }
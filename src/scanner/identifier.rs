@@ -45,16 +45,51 @@ impl Scanner<'_> {
                     TokenType::Identifier
                 }
             }
-            // Checks for keyword `if`
-            'i' => self.check_keyword(1, 1, "f", TokenType::If),
+            // Checks for keyword `global`
+            'g' => self.check_keyword(1, 5, "lobal", TokenType::Global),
+            // Checks for keywords `if` and `import`
+            'i' => {
+                if self.current - self.start > 1 {
+                    // Since more than 1 characters have been processed, it's save to unwrap second
+                    // character.
+                    let second_char = self.source[self.start + 1..].chars().next().unwrap();
+                    match second_char {
+                        // Checks for keyword `if`
+                        'f' => self.check_keyword(1, 1, "f", TokenType::If),
+                        // Checks for keyword `import`
+                        'm' => self.check_keyword(1, 5, "mport", TokenType::Import),
+                        // It's a custom Identifier
+                        _ => TokenType::Identifier,
+                    }
+                } else {
+                    // Not a keyword. Custom Identifier
+                    TokenType::Identifier
+                }
+            }
             // Checks for keyword `nil`
             'n' => self.check_keyword(1, 2, "il", TokenType::Nil),
             // Checks for keyword `or`
             'o' => self.check_keyword(1, 1, "r", TokenType::Or),
             // Checks for keyword `print`
             'p' => self.check_keyword(1, 4, "rint", TokenType::Print),
-            // Checks for keyword `return`
-            'r' => self.check_keyword(1, 5, "eturn", TokenType::Return),
+            // Checks for keywords `repeat` and `return`
+            'r' => {
+                if self.current - self.start > 2 {
+                    // Both keywords start with 're', third character tells them apart
+                    let third_char = self.source[self.start + 2..].chars().next().unwrap();
+                    match third_char {
+                        // Checks for keyword `repeat`
+                        'p' => self.check_keyword(1, 5, "epeat", TokenType::Repeat),
+                        // Checks for keyword `return`
+                        't' => self.check_keyword(1, 5, "eturn", TokenType::Return),
+                        // It's a custom Identifier
+                        _ => TokenType::Identifier,
+                    }
+                } else {
+                    // Not a keyword. Custom Identifier
+                    TokenType::Identifier
+                }
+            }
             // Checks for keyword `super`
             's' => self.check_keyword(1, 4, "uper", TokenType::Super),
             // Checks for multiple keywords starting with `t`
@@ -78,6 +113,8 @@ impl Scanner<'_> {
                     TokenType::Identifier
                 }
             }
+            // Checks for keyword `until`
+            'u' => self.check_keyword(1, 4, "ntil", TokenType::Until),
             // Checks for keyword `var`
             'v' => self.check_keyword(1, 2, "ar", TokenType::Var),
             // Checks for keyword `while`
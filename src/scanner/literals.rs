@@ -48,7 +48,12 @@ impl Scanner<'_> {
             // already at the end of the source.
             && !self.is_at_end()
         {
-            // This allows string to be multiline
+            // Escaped character, like '\"', shouldn't end the string. Consume '\' here and escaped character below.
+            // Escape sequences are decoded by the compiler.
+            if c == '\\' && self.peek_next().is_some() {
+                self.advance();
+            }
+            // This allows string to be multiline. For CRLF line endings, '\r' is consumed as an ordinary character, so line is only incremented once, at '\n'.
             if let Some(c) = self.peek()
                 && c == '\n'
             {
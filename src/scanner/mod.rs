@@ -21,6 +21,12 @@ pub struct Scanner<'a> {
     current: usize,
     /// Current line number
     line: i32,
+    /// Column of the character at `current`, counted in characters, not bytes. Updated by `advance`, so it isn't counted again for every token.
+    column: usize,
+    /// Column where current token starts
+    start_column: usize,
+    /// Whether iterator has yielded the `Eof` token
+    yielded_eof: bool,
 }
 
 impl<'a> Scanner<'a> {
@@ -32,6 +38,19 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_column: 1,
+            yielded_eof: false,
+        }
+    }
+
+    /// Same as `new`, but lines are numbered from `first_line`, e.g. when source is a line typed in REPL, and errors should
+    /// point at the line of the whole session
+    #[must_use]
+    pub fn starting_at_line(source: &'a str, first_line: i32) -> Self {
+        Self {
+            line: first_line,
+            ..Self::new(source)
         }
     }
 
@@ -56,6 +75,7 @@ impl<'a> Scanner<'a> {
         // Starting from where previous token scan left.
         // Both should be 0 when scanning first token
         self.start = self.current;
+        self.start_column = self.column;
 
         // If we've reached the end, just return `Eof` token
         if self.is_at_end() {
@@ -83,8 +103,11 @@ impl<'a> Scanner<'a> {
             ')' => self.make_token(TokenType::RightParen),
             '{' => self.make_token(TokenType::LeftBrace),
             '}' => self.make_token(TokenType::RightBrace),
+            '[' => self.make_token(TokenType::LeftBracket),
+            ']' => self.make_token(TokenType::RightBracket),
             ';' => self.make_token(TokenType::Semicolon),
             ',' => self.make_token(TokenType::Comma),
+            ':' => self.make_token(TokenType::Colon),
             '.' => self.make_token(TokenType::Dot),
             '-' => self.make_token(TokenType::Minus),
             '+' => self.make_token(TokenType::Plus),
@@ -107,6 +130,8 @@ impl<'a> Scanner<'a> {
                 let ty = self.pick_token_type('=', TokenType::GreaterEqual, TokenType::Greater);
                 self.make_token(ty)
             }
+            // Only `??` is an operator, a single '?' isn't valid
+            '?' if self.match_char('?') => self.make_token(TokenType::QuestionQuestion),
             '"' => self.string()?,
             _ => {
                 let err = ScannerError::UnexpectedCharacter {
@@ -203,6 +228,12 @@ impl<'a> Scanner<'a> {
         // So we can return byte as character
         let byte = self.source.as_bytes().get(self.current).copied()?;
         self.current += 1;
+        if byte == b'\n' {
+            self.column = 1;
+        } else if (byte & 0xC0) != 0x80 {
+            // UTF-8 continuation bytes belong to the character already counted
+            self.column += 1;
+        }
         Some(byte as char)
     }
 
@@ -218,7 +249,7 @@ impl<'a> Scanner<'a> {
             && c == expected
         {
             // Consume character to make token
-            self.current += 1;
+            self.advance();
             return true;
         }
 
@@ -232,6 +263,32 @@ impl<'a> Scanner<'a> {
 
     /// Makes a new token and return it
     fn make_token(&self, ty: TokenType) -> Token {
-        Token::new(ty, self.start, self.current - self.start, self.line)
+        Token::new(
+            ty,
+            self.start,
+            self.current - self.start,
+            self.line,
+            self.start_column,
+        )
+    }
+}
+
+/// Yields tokens until `Eof`, which is yielded once. Errors are yielded as well and scanning continues after them,
+/// because the invalid character has already been consumed.
+impl Iterator for Scanner<'_> {
+    type Item = Result<Token, ScannerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.yielded_eof {
+            return None;
+        }
+
+        let result = self.scan_token();
+        if let Ok(token) = &result
+            && token.ty == TokenType::Eof
+        {
+            self.yielded_eof = true;
+        }
+        Some(result)
     }
 }
@@ -17,14 +17,17 @@ fn skip_whitespace() {
 
 #[test]
 fn single_character_tokens() {
-    let source = "(){};,.-+/*! = ><";
+    let source = "(){}[];,:.-+/*! = ><";
     let token_tys = [
         TokenType::LeftParen,
         TokenType::RightParen,
         TokenType::LeftBrace,
         TokenType::RightBrace,
+        TokenType::LeftBracket,
+        TokenType::RightBracket,
         TokenType::Semicolon,
         TokenType::Comma,
+        TokenType::Colon,
         TokenType::Dot,
         TokenType::Minus,
         TokenType::Plus,
@@ -126,8 +129,8 @@ fn invalid_string_token() {
 
 #[test]
 fn identifiers() {
-    let source = "and or class if else false for fun 
-    true nil print return super this _this _class another_variable_name";
+    let source = "and or class if else false for fun global globals
+    true nil print return super this _this _class another_variable_name import i imports repeat repeats re until";
 
     let token_tys = [
         TokenType::And,
@@ -138,6 +141,8 @@ fn identifiers() {
         TokenType::False,
         TokenType::For,
         TokenType::Fun,
+        TokenType::Global,
+        TokenType::Identifier,
         TokenType::True,
         TokenType::Nil,
         TokenType::Print,
@@ -147,6 +152,13 @@ fn identifiers() {
         TokenType::Identifier,
         TokenType::Identifier,
         TokenType::Identifier,
+        TokenType::Import,
+        TokenType::Identifier,
+        TokenType::Identifier,
+        TokenType::Repeat,
+        TokenType::Identifier,
+        TokenType::Identifier,
+        TokenType::Until,
         TokenType::Eof,
     ];
     let mut scanner = Scanner::new(source);
@@ -157,3 +169,158 @@ fn identifiers() {
         index += 1;
     }
 }
+
+#[test]
+fn escaped_quote_in_string() {
+    let source = r#""say \"hi\"" "\\""#;
+    let mut scanner = Scanner::new(source);
+    let first = scanner.scan_token().unwrap();
+    let second = scanner.scan_token().unwrap();
+    assert_eq!(first.ty, TokenType::String);
+    assert_eq!(first.as_str(source), r#""say \"hi\"""#);
+    assert_eq!(second.ty, TokenType::String);
+    assert_eq!(second.as_str(source), r#""\\""#);
+    assert_eq!(scanner.scan_token().unwrap().ty, TokenType::Eof);
+}
+
+#[test]
+fn token_dump_format() {
+    use crate::debug::Debug;
+
+    let source = "var name;";
+    let mut scanner = Scanner::new(source);
+    let token = scanner.scan_token().unwrap();
+    assert_eq!(
+        Debug::token_to_string(&token, source),
+        "   1 Var          'var'"
+    );
+    let token = scanner.scan_token().unwrap();
+    assert_eq!(
+        Debug::token_to_string(&token, source),
+        "   1 Identifier   'name'"
+    );
+}
+
+#[test]
+fn crlf_line_endings() {
+    let source = "var a = \"one\r\ntwo\r\n\";\r\n// comment\r\nb";
+    let mut scanner = Scanner::new(source);
+    let mut tokens = vec![];
+    loop {
+        let token = scanner.scan_token().unwrap();
+        tokens.push((token.ty, token.line));
+        if token.ty == TokenType::Eof {
+            break;
+        }
+    }
+
+    assert_eq!(
+        tokens,
+        vec![
+            (TokenType::Var, 1),
+            (TokenType::Identifier, 1),
+            (TokenType::Equal, 1),
+            // Token gets line where the string ends
+            (TokenType::String, 3),
+            (TokenType::Semicolon, 3),
+            (TokenType::Identifier, 5),
+            (TokenType::Eof, 5),
+        ]
+    );
+}
+
+#[test]
+fn crlf_in_unterminated_string() {
+    let mut scanner = Scanner::new("\"one\r\ntwo\r\n");
+    assert_eq!(
+        scanner.scan_token().err(),
+        Some(ScannerError::UnterminatedString { line: 3 })
+    );
+}
+
+#[test]
+fn tokens_iterator() {
+    let source = "fun add(a, b) {\n  return a + b;\n}\nprint add(1, 2);";
+    let tokens: Vec<_> = Scanner::new(source)
+        .map(|result| result.map(|token| token.ty))
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        tokens,
+        vec![
+            TokenType::Fun,
+            TokenType::Identifier,
+            TokenType::LeftParen,
+            TokenType::Identifier,
+            TokenType::Comma,
+            TokenType::Identifier,
+            TokenType::RightParen,
+            TokenType::LeftBrace,
+            TokenType::Return,
+            TokenType::Identifier,
+            TokenType::Plus,
+            TokenType::Identifier,
+            TokenType::Semicolon,
+            TokenType::RightBrace,
+            TokenType::Print,
+            TokenType::Identifier,
+            TokenType::LeftParen,
+            TokenType::Number,
+            TokenType::Comma,
+            TokenType::Number,
+            TokenType::RightParen,
+            TokenType::Semicolon,
+            TokenType::Eof,
+        ]
+    );
+}
+
+#[test]
+fn tokens_iterator_stops_after_eof() {
+    let mut scanner = Scanner::new("a @ b");
+    assert_eq!(scanner.next().unwrap().unwrap().ty, TokenType::Identifier);
+    assert_eq!(
+        scanner.next().unwrap().err(),
+        Some(ScannerError::UnexpectedCharacter {
+            line: 1,
+            character: '@'
+        })
+    );
+    assert_eq!(scanner.next().unwrap().unwrap().ty, TokenType::Identifier);
+    assert_eq!(scanner.next().unwrap().unwrap().ty, TokenType::Eof);
+    assert!(scanner.next().is_none());
+    assert!(scanner.next().is_none());
+}
+
+#[test]
+fn token_columns() {
+    let source = "var a = 1;\n  print \"é\" + a;\n\"two\nlines\" b";
+    let columns: Vec<_> = Scanner::new(source)
+        .map(|token| {
+            let token = token.unwrap();
+            (token.as_str(source), token.line, token.column)
+        })
+        .collect();
+
+    assert_eq!(
+        columns,
+        vec![
+            ("var".to_owned(), 1, 1),
+            ("a".to_owned(), 1, 5),
+            ("=".to_owned(), 1, 7),
+            ("1".to_owned(), 1, 9),
+            (";".to_owned(), 1, 10),
+            ("print".to_owned(), 2, 3),
+            ("\"é\"".to_owned(), 2, 9),
+            // 'é' takes two bytes, but it's a single column
+            ("+".to_owned(), 2, 13),
+            ("a".to_owned(), 2, 15),
+            (";".to_owned(), 2, 16),
+            // Multi-line string has column of its start, but line of its end
+            ("\"two\nlines\"".to_owned(), 4, 1),
+            ("b".to_owned(), 4, 8),
+            (String::new(), 4, 9),
+        ]
+    );
+}
@@ -8,7 +8,10 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
@@ -24,6 +27,7 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    QuestionQuestion,
     // Literals
     Identifier,
     String,
@@ -35,14 +39,18 @@ pub enum TokenType {
     False,
     For,
     Fun,
+    Global,
     If,
+    Import,
     Nil,
     Or,
     Print,
+    Repeat,
     Return,
     Super,
     This,
     True,
+    Until,
     Var,
     While,
 
@@ -63,23 +71,35 @@ pub struct Token {
     pub length: usize,
     /// In which line of the source code the token appeares.
     pub line: i32,
+    /// Column of the first character of the token in its line, starting from 1. Multi-byte characters are counted once.
+    pub column: usize,
 }
 
 impl Token {
     /// Returns the fresh instance of Token
     #[must_use]
-    pub fn new(ty: TokenType, start: usize, length: usize, line: i32) -> Self {
+    pub fn new(ty: TokenType, start: usize, length: usize, line: i32, column: usize) -> Self {
         Self {
             ty,
             start,
             length,
             line,
+            column,
         }
     }
 
     /// Returns string form of current token
     #[must_use]
     pub fn as_str(&self, source: &str) -> String {
-        source[self.start..self.start + self.length].to_owned()
+        self.lexeme(source).to_owned()
+    }
+
+    /// Returns the token's text borrowed from the source, without allocating.
+    ///
+    /// Token doesn't cache its text. Slicing the source is just pointer arithmetic, while caching would cost an allocation
+    /// for every scanned token. It's allocating a `String` in `as_str`, which should be avoided in hot paths, like resolving locals.
+    #[must_use]
+    pub fn lexeme<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.start + self.length]
     }
 }
@@ -0,0 +1,63 @@
+//! Helpers shared by tests of different modules
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::{Cell, RefCell},
+    io::Write,
+    rc::Rc,
+};
+
+/// Buffer shared with the `VM`, to read what it printed
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(pub(crate) Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Bytes allocated and not freed yet by the current thread. Tests run in parallel, so counting per thread keeps them apart.
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Allocator of the test binary. Counts memory allocated by every thread, so tests can check that memory is given back.
+struct CountingAllocator;
+
+impl CountingAllocator {
+    fn count(bytes: isize) {
+        // Counter has no destructor, but it may not be accessible while a thread is being torn down
+        let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + bytes));
+    }
+}
+
+// SAFETY: Every call is forwarded to the system allocator as it is
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size().cast_signed());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Self::count(-layout.size().cast_signed());
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::count(new_size.cast_signed() - layout.size().cast_signed());
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns bytes allocated by the current thread, and not freed yet
+pub(crate) fn allocated_bytes() -> isize {
+    ALLOCATED.with(Cell::get)
+}
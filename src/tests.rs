@@ -1,6 +1,9 @@
+/// Runs every file and compares lines it printed with `// expect: ` comments in it, in order. Files are run with errors returned,
+/// instead of exiting the process, so a failing file points at itself. A file without expectations, like `fibonacci` which
+/// prints how long it took, only has to run without errors.
 #[test]
 fn test_lox_files() {
-    use crate::run_file;
+    use std::{fs, path::Path};
 
     let base_directory = "lox/";
     let files = [
@@ -10,11 +13,1278 @@ fn test_lox_files() {
         "functions/functions_2",
         "functions/functions",
         "functions/recursion",
+        "imports/main",
         "mixed_types_expression",
         "scopes",
+        "self_checking",
     ];
 
     for file in files {
-        run_file(&(base_directory.to_owned() + file + ".lox"));
+        let path = base_directory.to_owned() + file + ".lox";
+        let source = fs::read_to_string(&path).unwrap();
+        let output = run_capturing_output(&source, Path::new(&path))
+            .unwrap_or_else(|e| panic!("{path} failed: {e}"));
+
+        let expected = expectations(&source);
+        if expected.is_empty() {
+            continue;
+        }
+        // Trailing spaces, like the ones printed between numbers, aren't visible in comments, so they're ignored
+        let printed: Vec<&str> = output.lines().map(str::trim_end).collect();
+        assert_eq!(
+            printed, expected,
+            "Output of {path} doesn't match its expectations"
+        );
+    }
+}
+
+/// Returns text after every `// expect: ` comment in the source, one for each line the script should print
+#[cfg(test)]
+fn expectations(source: &str) -> Vec<&str> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let (_, expected) = line.split_once("// expect:")?;
+            // Only the single space separating the comment from the text is removed, so leading spaces can be expected
+            Some(expected.strip_prefix(' ').unwrap_or(expected).trim_end())
+        })
+        .collect()
+}
+
+/// Runs source read from `path` on a fresh `VM`, and returns everything it printed
+#[cfg(test)]
+fn run_capturing_output(
+    source: &str,
+    path: &std::path::Path,
+) -> Result<String, crate::vm::errors::VMError> {
+    use crate::{interpret_with_warnings, test_utils::SharedBuffer, vm::VM};
+
+    let mut vm = VM::new();
+    let buffer = SharedBuffer::default();
+    vm.set_output(buffer.clone());
+    let result = interpret_with_warnings(source, &mut vm, false, Some(path));
+    vm.reset_vm();
+    result?;
+
+    let output = buffer.0.borrow().clone();
+    Ok(String::from_utf8(output).expect("Output should be valid UTF-8"))
+}
+
+#[test]
+fn test_expectations_are_read_from_comments() {
+    let source = "println(1); // expect: 1\n// Not an expectation\nprint \"  a \"; println(); // expect:   a\n// expect:";
+    assert_eq!(expectations(source), ["1", "  a", ""]);
+}
+
+#[test]
+fn test_eval_returns_script_result() {
+    use crate::{eval, value::Value};
+
+    assert_eq!(eval("var a = 1 + 2;").unwrap(), Value::new_nil());
+    assert_eq!(eval("").unwrap(), Value::new_nil());
+    // Last expression without ';' is the result of the script
+    assert_eq!(eval("var a = 1 + 2;\na * 2").unwrap(), Value::from(6.0));
+    assert_eq!(eval("1 + 2").unwrap(), Value::from(3.0));
+    assert_eq!(eval(r#""a" + "b""#).unwrap(), Value::from("ab".to_owned()));
+}
+
+#[test]
+fn test_eval_returns_errors() {
+    use crate::{eval, vm::errors::VMError};
+
+    assert!(matches!(eval("var a = ;"), Err(VMError::CompileError(_))));
+    assert!(matches!(
+        eval("-\"not a number\";"),
+        Err(VMError::RuntimeError(_))
+    ));
+    assert!(matches!(
+        eval("undefined_variable;"),
+        Err(VMError::RuntimeError(_))
+    ));
+}
+
+#[test]
+fn test_eval_unpack_mismatch() {
+    use crate::{eval, vm::errors::VMError};
+
+    let code = "fun f() { return 1, 2; } var (a, b) = f();";
+    assert!(eval(code).is_ok());
+
+    let code = "fun f() { return 1, 2; } var (a, b, c) = f();";
+    assert!(
+        matches!(eval(code), Err(VMError::RuntimeError(e)) if e.contains("Expected 3 values to unpack but got 2."))
+    );
+
+    let code = "fun f() { return 1; } var (a, b) = f();";
+    assert!(matches!(eval(code), Err(VMError::RuntimeError(_))));
+}
+
+#[test]
+fn test_eval_arity_error_names_function() {
+    use crate::{eval, vm::errors::VMError};
+
+    let code = "fun add(a, b) { return a + b; } add(1);";
+    assert!(
+        matches!(eval(code), Err(VMError::RuntimeError(e)) if e.starts_with("Expected 2 arguments but got 1 when calling 'add'."))
+    );
+}
+
+#[test]
+fn test_pre_seeded_globals() {
+    use crate::{interpret, value::Value, vm::VM};
+
+    let mut vm = VM::new();
+    vm.set_global("limit", Value::from(10.0));
+    interpret("var doubled = limit * 2;", &mut vm).unwrap();
+
+    assert_eq!(vm.get_global("doubled"), Some(Value::from(20.0)));
+    assert_eq!(vm.get_global("undefined"), None);
+    vm.reset_vm();
+}
+
+#[test]
+fn test_strict_globals() {
+    use crate::{interpret, vm::VM, vm::errors::VMError};
+
+    let mut vm = VM::new();
+    vm.strict_globals = true;
+    interpret("var a = 1;", &mut vm).unwrap();
+    assert!(matches!(
+        interpret("var a = 2;", &mut vm),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Global 'a' is already defined.")
+    ));
+    assert!(matches!(
+        interpret("fun clock() {}", &mut vm),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Can't redefine native function 'clock'.")
+    ));
+    vm.reset_vm();
+}
+
+#[test]
+fn test_function_equality_is_identity() {
+    use crate::{interpret, value::Value, vm::VM};
+
+    let mut vm = VM::new();
+    let code = "
+        fun f() { return 1; }
+        fun g() { return 1; }
+        var h = f;
+        var same = f == h;
+        var different = f == g;
+    ";
+    interpret(code, &mut vm).unwrap();
+
+    assert_eq!(vm.get_global("same"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("different"), Some(Value::from(false)));
+    vm.reset_vm();
+}
+
+#[test]
+fn test_concatenation_keeps_operands() {
+    use crate::{interpret, vm::VM};
+
+    let mut vm = VM::new();
+    let code = r#"
+        var s = "a" + "b";
+        var t = s + "c";
+        var u = s + "d";
+        fun f() {}
+        var g = "fn " + f;
+    "#;
+    interpret(code, &mut vm).unwrap();
+
+    assert_eq!(vm.get_global("u").unwrap().as_string_ref(), "abd");
+    assert_eq!(vm.get_global("t").unwrap().as_string_ref(), "abc");
+    assert_eq!(vm.get_global("g").unwrap().as_string_ref(), "fn <fn f>");
+    vm.reset_vm();
+}
+
+#[test]
+fn test_invalid_operands_error_names_types() {
+    use crate::{eval, vm::errors::VMError};
+
+    assert!(matches!(
+        eval("true < 3;"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Invalid operation on operands of type 'bool' and 'number'.")
+    ));
+    assert!(matches!(
+        eval("fun f() {} nil * f;"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Invalid operation on operands of type 'nil' and 'function'.")
+    ));
+}
+
+#[test]
+fn test_empty_sources_are_no_op() {
+    use crate::{eval, value::Value};
+
+    for source in [
+        "",
+        "  \n\t\r\n  ",
+        "// only a comment",
+        "// comment\n\n// another\n",
+    ] {
+        assert_eq!(eval(source).unwrap(), Value::new_nil(), "{source:?}");
+    }
+}
+
+#[test]
+fn test_interpret_keeps_globals_between_runs() {
+    use crate::{interpret, vm::VM};
+
+    let mut vm = VM::new();
+    interpret(r#"var s = "a" + "b";"#, &mut vm).unwrap();
+    assert!(vm.stack.is_empty() && vm.frames.is_empty());
+
+    // Runtime error resets stack and frames, but keeps globals and their objects
+    assert!(interpret("var x = 1; -nil;", &mut vm).is_err());
+    assert!(vm.stack.is_empty() && vm.frames.is_empty());
+
+    interpret(r#"var t = s + "c";"#, &mut vm).unwrap();
+    assert_eq!(vm.get_global("t").unwrap().as_string_ref(), "abc");
+    assert!(vm.get_global("x").is_some());
+    vm.reset_vm();
+}
+
+#[test]
+fn test_comparisons_with_nan() {
+    use crate::{eval, value::Value};
+
+    for source in [
+        "0/0 >= 1", "1 >= 0/0", "0/0 <= 1", "1 <= 0/0", "0/0 > 1", "0/0 < 1",
+    ] {
+        assert_eq!(eval(source).unwrap(), Value::from(false), "{source}");
+    }
+    assert_eq!(eval("2 >= 2").unwrap(), Value::from(true));
+    assert_eq!(eval("1 >= 2").unwrap(), Value::from(false));
+    assert_eq!(eval("2 <= 2").unwrap(), Value::from(true));
+    assert_eq!(eval("3 <= 2").unwrap(), Value::from(false));
+}
+
+#[test]
+fn test_calling_temporaries() {
+    use crate::{eval, value::Value};
+
+    let functions = "
+        fun one() { return 1; }
+        fun getFn() { return one; }
+        fun adder(a, b) { return a + b; }
+        fun pick() { return adder, one; }
+    ";
+    // Function returned from another function
+    assert_eq!(
+        eval(&format!("{functions} getFn()()")).unwrap(),
+        Value::from(1.0)
+    );
+    // Function stored in a local variable
+    assert_eq!(
+        eval(&format!(
+            "{functions} fun run() {{ var f = getFn(); return f() + 1; }} run()"
+        ))
+        .unwrap(),
+        Value::from(2.0)
+    );
+    // Functions unpacked from multiple return values
+    assert_eq!(
+        eval(&format!(
+            "{functions} fun run() {{ var (add, o) = pick(); return add(o(), 2); }} run()"
+        ))
+        .unwrap(),
+        Value::from(3.0)
+    );
+}
+
+#[test]
+fn test_string_length_natives() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    assert_eq!(eval(r#"len("hello")"#).unwrap(), Value::from(5.0));
+    assert_eq!(eval(r#"len("a\nb")"#).unwrap(), Value::from(3.0));
+    assert_eq!(eval(r#"len("\u{1F600}é")"#).unwrap(), Value::from(2.0));
+    assert_eq!(eval(r#"byte_len("\u{1F600}é")"#).unwrap(), Value::from(6.0));
+    assert_eq!(eval(r#"len("ab" + "c")"#).unwrap(), Value::from(3.0));
+    assert_eq!(eval(r#"byte_len("")"#).unwrap(), Value::from(0.0));
+
+    assert!(matches!(
+        eval("len(10)"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("len() expects a string but got 'number'.")
+    ));
+    assert!(matches!(
+        eval("byte_len()"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Expected 1 arguments but got 0 when calling 'byte_len'.")
+    ));
+}
+
+#[test]
+fn test_exit_native() {
+    use crate::{eval, interpret, value::Value, vm::VM, vm::errors::VMError};
+
+    assert!(matches!(eval("exit(0);"), Err(VMError::Exit(0))));
+    assert!(matches!(eval("exit(255);"), Err(VMError::Exit(255))));
+
+    // Exit from a nested call stops the whole script, and VM unwinds
+    let mut vm = VM::new();
+    let result = interpret(
+        "var a = 1; fun stop() { exit(3); a = 2; } stop(); a = 3;",
+        &mut vm,
+    );
+    assert!(matches!(result, Err(VMError::Exit(3))));
+    assert_eq!(vm.get_global("a"), Some(Value::from(1.0)));
+    assert!(vm.stack.is_empty());
+    assert!(vm.frames.is_empty());
+    vm.reset_vm();
+
+    for code in ["exit(256);", "exit(-1);", "exit(1.5);", "exit(\"1\");"] {
+        assert!(matches!(
+            eval(code),
+            Err(VMError::RuntimeError(e)) if e.starts_with("exit() expects a whole number between 0 and 255 but got")
+        ));
+    }
+    assert!(matches!(
+        eval("exit();"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Expected 1 arguments but got 0 when calling 'exit'.")
+    ));
+}
+
+#[test]
+fn test_builtin_properties() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    assert_eq!(eval(r#""hello".length"#).unwrap(), Value::from(5.0));
+    assert_eq!(eval(r#""".length"#).unwrap(), Value::from(0.0));
+    assert_eq!(
+        eval(r#"var s = "h\u{E9}llo"; s.length"#).unwrap(),
+        Value::from(5.0)
+    );
+    assert_eq!(eval(r#"("ab" + "c").length"#).unwrap(), Value::from(3.0));
+    // Property access binds tighter than unary and binary operators
+    assert_eq!(eval(r#"-"ab".length"#).unwrap(), Value::from(-2.0));
+    assert_eq!(
+        eval(r#""ab" + "c".length"#).unwrap(),
+        Value::from("ab1".to_owned())
+    );
+
+    let code = "fun pair() { return 1, 2; } var values = pair(); values.length";
+    assert_eq!(eval(code).unwrap(), Value::from(2.0));
+
+    assert!(matches!(
+        eval(r#""hello".size"#),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Undefined property 'size' on 'string'.")
+    ));
+    assert!(matches!(
+        eval("var a = 10; a.length"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Only strings and arrays have properties, 'number' doesn't.")
+    ));
+    assert!(matches!(
+        eval(r#""hello".length = 1;"#),
+        Err(VMError::CompileError(e)) if e.to_string().contains("Invalid assignment target.")
+    ));
+    assert!(matches!(
+        eval(r#""hello".;"#),
+        Err(VMError::CompileError(e)) if e.to_string().contains("Expected property name after '.'.")
+    ));
+}
+
+#[test]
+fn test_format_native() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    let code = r#"var x = 1; var y = "two"; format("x = {}, y = {}", x, y)"#;
+    assert_eq!(
+        eval(code).unwrap(),
+        Value::from("x = 1, y = two".to_owned())
+    );
+    assert_eq!(
+        eval(r#"format("{{}} {} {}", nil, 2.5)"#).unwrap(),
+        Value::from("{} nil 2.5".to_owned())
+    );
+    assert_eq!(
+        eval(r#"format("no placeholders")"#).unwrap(),
+        Value::from("no placeholders".to_owned())
+    );
+    // Result is a string, so it can be used like one
+    assert_eq!(
+        eval(r#"format("{}{}", 1, 2).length"#).unwrap(),
+        Value::from(2.0)
+    );
+
+    let errors = [
+        (
+            r#"format("{} {}", 1)"#,
+            "format() has 2 placeholders but got 1 arguments.",
+        ),
+        (
+            r#"format("{}", 1, 2)"#,
+            "format() has 1 placeholders but got 2 arguments.",
+        ),
+        (r#"format("{", 1)"#, "Invalid format string: unmatched '{'."),
+        (
+            r#"format("a } b")"#,
+            "Invalid format string: unmatched '}'.",
+        ),
+        ("format(1)", "format() expects a string but got 'number'."),
+        (
+            "format()",
+            "Expected at least 1 arguments but got 0 when calling 'format'.",
+        ),
+    ];
+    for (code, expected) in errors {
+        let result = eval(code);
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with(expected)),
+            "{result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_assignment_expression_value() {
+    use crate::{eval, value::Value};
+
+    let result = |code: &str| eval(code).unwrap();
+    let expected = Value::from("5 5".to_owned());
+
+    // Globals
+    assert_eq!(
+        result(r#"var a; var b = (a = 5); format("{} {}", a, b)"#),
+        expected
+    );
+    // Locals
+    assert_eq!(
+        result(r#"var result; { var a; var b = (a = 5); result = format("{} {}", a, b); } result"#),
+        expected
+    );
+    // Local assigned to global and the other way around
+    assert_eq!(
+        result(r#"var g; { var l; g = (l = 5); g = format("{} {}", g, l); } g"#),
+        expected
+    );
+    assert_eq!(
+        result(r#"var g; var out; { var l = (g = 5); out = format("{} {}", g, l); } out"#),
+        expected
+    );
+    // Assignment is right associative
+    assert_eq!(
+        result(r#"var a; var b; a = b = 5; format("{} {}", a, b)"#),
+        expected
+    );
+    // Value of the assignment can be used in other expressions
+    assert_eq!(result("var a; (a = 2) * 3"), Value::from(6.0));
+}
+
+#[test]
+fn test_repl_session_ending_in_eof_frees_objects() {
+    use clap::Parser;
+
+    use crate::{
+        cli::{Cli, run_repl},
+        vm::VM,
+    };
+
+    let cli = Cli::parse_from(["rslox"]);
+    for session in [
+        "var s = \"a\" + \"b\";\ns + \"c\"\n",
+        "var s = \"a\" + \"b\";\nexit\n",
+    ] {
+        let mut vm = VM::new();
+        let mut output = Vec::new();
+        let result = run_repl(&cli, &mut session.as_bytes(), &mut output, &mut vm);
+
+        assert!(result.is_ok(), "{session:?}: {result:?}");
+        assert!(vm.objects.is_none(), "{session:?}");
+        assert!(vm.stack.is_empty() && vm.frames.is_empty());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("> > "), "{output:?}");
+    }
+}
+
+#[test]
+fn test_scripts_are_freed_after_running() {
+    use clap::Parser;
+
+    use crate::{
+        chunk::{Chunk, OpCode},
+        cli::{Cli, run_repl},
+        interpret,
+        test_utils::allocated_bytes,
+        value::Value,
+        vm::VM,
+    };
+
+    // Literal is a constant of the script, so it's only freed with the script
+    let literal = "x".repeat(1 << 20);
+    let line = format!("len(\"{literal}\");\n");
+    let leaked = |run: &mut dyn FnMut(&mut VM)| {
+        let mut vm = VM::new();
+        // First run grows the stack and globals, which are kept by the VM
+        run(&mut vm);
+        vm.reset_vm();
+        let before = allocated_bytes();
+        for _ in 0..5 {
+            run(&mut vm);
+        }
+        vm.reset_vm();
+        allocated_bytes() - before
+    };
+
+    let bytes = leaked(&mut |vm| {
+        interpret(&line, vm).unwrap();
+    });
+    assert!(bytes < 1 << 16, "interpret leaked {bytes} bytes");
+
+    let bytes = leaked(&mut |vm| {
+        let mut chunk = Chunk::new();
+        let constant = chunk.add_constant(Value::from(literal.clone())).unwrap();
+        for byte in [OpCode::OpConstant as u8, constant, OpCode::OpReturn as u8] {
+            chunk.write_chunk(byte, 1);
+        }
+        vm.run_chunk(chunk).unwrap();
+    });
+    assert!(bytes < 1 << 16, "run_chunk leaked {bytes} bytes");
+
+    let cli = Cli::parse_from(["rslox"]);
+    let session = line.repeat(5);
+    let bytes = leaked(&mut |vm| {
+        run_repl(&cli, &mut session.as_bytes(), &mut Vec::new(), vm).unwrap();
+    });
+    assert!(bytes < 1 << 16, "REPL leaked {bytes} bytes");
+}
+
+#[test]
+fn test_repl_exit_native_is_returned_to_caller() {
+    use clap::Parser;
+
+    use crate::{
+        cli::{Cli, run_repl},
+        vm::{VM, errors::VMError},
+    };
+
+    let cli = Cli::parse_from(["rslox"]);
+    let mut vm = VM::new();
+    let mut output = Vec::new();
+    let session = "var s = \"a\" + \"b\";\nexit(3);\nprint \"not reached\";\n";
+    let result = run_repl(&cli, &mut session.as_bytes(), &mut output, &mut vm);
+
+    assert!(matches!(result, Err(VMError::Exit(3))), "{result:?}");
+    assert_eq!(result.unwrap_err().exit_code(), 3);
+    // Session is cleaned up before returning, and lines after `exit()` aren't read
+    assert!(vm.objects.is_none() && vm.stack.is_empty());
+    assert_eq!(String::from_utf8(output).unwrap(), "> > ");
+}
+
+#[test]
+fn test_min_max_natives() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    assert_eq!(eval("min(4, -3, 0)").unwrap(), Value::from(-3.0));
+    assert_eq!(eval("max(4, -3, 0)").unwrap(), Value::from(4.0));
+    assert_eq!(eval("min(-1.5, -2.5)").unwrap(), Value::from(-2.5));
+    assert_eq!(eval("max(-1.5, -2.5)").unwrap(), Value::from(-1.5));
+    assert_eq!(eval("max(7, 7)").unwrap(), Value::from(7.0));
+    // `NaN` propagates, wherever it is
+    for code in ["min(0/0, 1)", "min(1, 0/0)", "max(1, 2, 0/0)"] {
+        assert!(eval(code).unwrap().to_number().is_nan(), "{code}");
+    }
+
+    let errors = [
+        (
+            "min(1)",
+            "Expected at least 2 arguments but got 1 when calling 'min'.",
+        ),
+        (
+            "max()",
+            "Expected at least 2 arguments but got 0 when calling 'max'.",
+        ),
+        (r#"min(1, "2")"#, "min() expects numbers but got 'string'."),
+        ("max(nil, 1)", "max() expects numbers but got 'nil'."),
+    ];
+    for (code, expected) in errors {
+        let result = eval(code);
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with(expected)),
+            "{result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_imports() {
+    use std::path::Path;
+
+    use crate::{interpret_with_warnings, value::Value, vm::VM, vm::errors::VMError};
+
+    // Path of the importing file doesn't need to exist, only its directory is used
+    let run = |source: &str| {
+        let mut vm = VM::new();
+        let result = interpret_with_warnings(
+            source,
+            &mut vm,
+            false,
+            Some(Path::new("lox/imports/test.lox")),
+        );
+        vm.reset_vm();
+        result
+    };
+
+    // Imported file imports another one, relative to its own directory
+    assert_eq!(
+        run(r#"import "math.lox"; square(3) + to_cm(1)"#).unwrap(),
+        Value::from(11.54)
+    );
+    // Imported code runs where it's imported
+    assert_eq!(
+        run(r#"var CM_PER_INCH = 3; import "constants/units.lox"; CM_PER_INCH"#).unwrap(),
+        Value::from(2.54)
+    );
+    assert_eq!(
+        run(r#"import "constants/units.lox"; CM_PER_INCH = 3; CM_PER_INCH"#).unwrap(),
+        Value::from(3.0)
+    );
+
+    let errors = [
+        (
+            r#"import "circular/a.lox";"#,
+            "In imported file 'circular/a.lox': In imported file 'b.lox': [line 1:8] Error at '\"a.lox\"': Circular import of 'a.lox'.",
+        ),
+        (r#"import "missing.lox";"#, "Can't import 'missing.lox': "),
+        (
+            r#"import "broken.lox";"#,
+            "In imported file 'broken.lox': [line 1:9] Error at ';': Expected expression",
+        ),
+        (r#"{ import "math.lox"; }"#, "Can only import at top level."),
+        (
+            r#"if (true) import "math.lox";"#,
+            "Can only import at top level.",
+        ),
+        (
+            r#"fun f() { import "math.lox"; }"#,
+            "Can only import at top level.",
+        ),
+        (r#"import math;"#, "Expected file path after 'import'."),
+        (r#"import "math.lox""#, "Expected ';' after import."),
+    ];
+    for (source, expected) in errors {
+        let result = run(source);
+        assert!(
+            matches!(&result, Err(VMError::CompileError(e)) if e.to_string().contains(expected)),
+            "{source}: {result:?}"
+        );
     }
 }
+
+#[test]
+fn test_repl_runtime_errors_report_line_of_session() {
+    use clap::Parser;
+
+    use crate::{
+        cli::{Cli, run_repl},
+        vm::VM,
+    };
+
+    let cli = Cli::parse_from(["rslox"]);
+    let mut vm = VM::new();
+    let mut output = Vec::new();
+    run_repl(
+        &cli,
+        &mut "var a = 1;\na + nil;\n".as_bytes(),
+        &mut output,
+        &mut vm,
+    )
+    .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("[line 2] in <script>"), "{output:?}");
+    assert!(!output.contains("[line 1]"), "{output:?}");
+
+    // Functions declared on a line, and compile errors, point at the line of the session too
+    let mut output = Vec::new();
+    run_repl(
+        &cli,
+        &mut "var a = 1;\n\n\nfun f() { return -nil; }\nprint 1 +\nf();\n".as_bytes(),
+        &mut output,
+        &mut vm,
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("[line 5:10] Error at end"), "{output:?}");
+    assert!(
+        output.contains("[line 4] in f()\n[line 6] in <script>"),
+        "{output:?}"
+    );
+}
+
+#[test]
+fn test_null_coalescing() {
+    use crate::{eval, value::Value};
+
+    assert_eq!(eval("nil ?? 5").unwrap(), Value::from(5.0));
+    assert_eq!(eval("3 ?? 5").unwrap(), Value::from(3.0));
+    // Only nil is replaced, falsey values are kept
+    assert_eq!(eval("false ?? 5").unwrap(), Value::from(false));
+    assert_eq!(
+        eval("var a; var b; var c = 7; a ?? b ?? c").unwrap(),
+        Value::from(7.0)
+    );
+    assert_eq!(
+        eval("var a; var b = 2; a ?? b ?? 9").unwrap(),
+        Value::from(2.0)
+    );
+    // Lower precedence than `or`
+    assert_eq!(eval("nil ?? false or 4").unwrap(), Value::from(4.0));
+    // Right operand isn't evaluated if left one isn't nil
+    assert_eq!(
+        eval("var calls = 0; fun f() { calls = calls + 1; return 1; } 2 ?? f(); calls").unwrap(),
+        Value::from(0.0)
+    );
+    // Single '?' isn't an operator
+    assert!(eval("1 ? 2").is_err());
+}
+
+#[test]
+fn test_compile_without_running() {
+    use crate::{compile, interpret, value::Value, vm::VM};
+
+    let function = compile("count = count + 1; count").unwrap();
+    assert_eq!(function.arity, 0);
+    assert!(function.name.is_none());
+
+    // Compiled once, run more than once. Globals persist between runs on the same VM.
+    let function = Value::from(function);
+    let mut vm = VM::new();
+    interpret("var count = 0;", &mut vm).unwrap();
+    assert_eq!(vm.run_isolated(function.clone()).unwrap(), Value::from(1.0));
+    assert_eq!(vm.run_isolated(function.clone()).unwrap(), Value::from(2.0));
+    vm.free_script(function);
+    vm.reset_vm();
+
+    let error = compile("var = 1;").unwrap_err();
+    assert!(error.to_string().contains("Expect"), "{error}");
+}
+
+#[test]
+fn test_repeat_until() {
+    use crate::{eval, value::Value};
+
+    let code = "var i = 0; var sum = 0; repeat { i = i + 1; sum = sum + i; } until (i == 4); sum";
+    assert_eq!(eval(code).unwrap(), Value::from(10.0));
+    // Body runs once, even if condition is already true
+    assert_eq!(
+        eval("var runs = 0; repeat runs = runs + 1; until (true); runs").unwrap(),
+        Value::from(1.0)
+    );
+    // Truthy values end the loop too
+    assert_eq!(
+        eval("var s; repeat s = \"done\"; until (s); s").unwrap(),
+        Value::from("done".to_owned())
+    );
+}
+
+#[test]
+fn test_assert_eq_native() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    assert_eq!(
+        eval(r#"assert_eq("a" + "b", "ab")"#).unwrap(),
+        Value::new_nil()
+    );
+    let error = eval(r#"assert_eq(1 + 1, "2");"#).unwrap_err();
+    assert!(
+        matches!(&error, VMError::RuntimeError(e) if e.starts_with(r#"Assertion failed: 2 != "2""#)),
+        "{error:?}"
+    );
+    assert!(matches!(
+        eval("assert_eq(1);"),
+        Err(VMError::RuntimeError(_))
+    ));
+}
+
+#[test]
+fn test_run_file_returns_errors() {
+    use crate::{run_file, vm::errors::VMError};
+
+    assert!(run_file("lox/scopes.lox", false, false).is_ok());
+    assert!(matches!(
+        run_file("lox/imports/broken.lox", false, false),
+        Err(VMError::CompileError(_))
+    ));
+    assert!(matches!(
+        run_file("lox/does_not_exist.lox", false, false),
+        Err(VMError::IoError(e)) if e.starts_with("Can't read code from file lox/does_not_exist.lox")
+    ));
+}
+
+#[test]
+fn test_dump_tokens_returns_errors() {
+    use std::fs;
+
+    use crate::{dump_tokens, vm::errors::VMError};
+
+    assert!(dump_tokens("lox/scopes.lox").is_ok());
+    let path = std::env::temp_dir().join("rslox_dump_tokens_unterminated.lox");
+    fs::write(&path, "var a = \"x;\n").unwrap();
+    let result = dump_tokens(path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+    assert!(
+        matches!(&result, Err(VMError::CompileError(e)) if e.to_string().contains("Unterminated string.")),
+        "{result:?}"
+    );
+    assert_eq!(result.unwrap_err().exit_code(), 65);
+
+    let result = dump_tokens("lox/does_not_exist.lox");
+    assert!(matches!(&result, Err(VMError::IoError(_))), "{result:?}");
+    assert_eq!(result.unwrap_err().exit_code(), 74);
+}
+
+#[test]
+fn test_disassemble_file_returns_errors() {
+    use crate::{disassemble_file, vm::errors::VMError};
+
+    assert!(disassemble_file("lox/scopes.lox").is_ok());
+    let result = disassemble_file("lox/imports/broken.lox");
+    assert!(
+        matches!(&result, Err(VMError::CompileError(_))),
+        "{result:?}"
+    );
+    assert_eq!(result.unwrap_err().exit_code(), 65);
+    let result = disassemble_file("lox/does_not_exist.lox");
+    assert!(matches!(&result, Err(VMError::IoError(_))), "{result:?}");
+    assert_eq!(result.unwrap_err().exit_code(), 74);
+}
+
+#[test]
+fn test_array_concatenation() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    let pairs = "fun pair(a, b) { return a, b; } ";
+    let code = format!("{pairs} pair(1, 2) + pair(3, 4)");
+    assert_eq!(eval(&code).unwrap(), Value::from("[1, 2, 3, 4]".to_owned()));
+    // Elements keep their order, and operands aren't changed
+    let code =
+        format!("{pairs} var a = pair(\"x\", 1); var b = pair(nil, true); var c = b + a; a + c");
+    assert_eq!(
+        eval(&code).unwrap(),
+        Value::from("[x, 1, nil, true, x, 1]".to_owned())
+    );
+    let code = format!("{pairs} (pair(1, 2) + pair(3, 4) + pair(5, 6)).length");
+    assert_eq!(eval(&code).unwrap(), Value::from(6.0));
+
+    for (operand, type_name) in [("1", "number"), ("\"s\"", "string"), ("nil", "nil")] {
+        let code = format!("{pairs} pair(1, 2) + {operand};");
+        assert!(
+            matches!(eval(&code), Err(VMError::RuntimeError(e)) if e.starts_with(&format!("Invalid operation on operands of type 'array' and '{type_name}'."))),
+            "{code}"
+        );
+        let code = format!("{pairs} {operand} + pair(1, 2);");
+        assert!(
+            matches!(eval(&code), Err(VMError::RuntimeError(e)) if e.starts_with(&format!("Invalid operation on operands of type '{type_name}' and 'array'."))),
+            "{code}"
+        );
+    }
+    assert!(matches!(
+        eval(&format!("{pairs} pair(1, 2) - pair(1, 2);")),
+        Err(VMError::RuntimeError(_))
+    ));
+}
+
+#[test]
+fn test_contains_native() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    let pairs = "fun pair(a, b) { return a, b; } ";
+    for (code, expected) in [
+        ("contains(pair(1, 3), 3)", true),
+        ("contains(pair(1, 3), 2)", false),
+        // Elements are compared by content, not by identity
+        ("contains(pair(\"a\" + \"b\", nil), \"ab\")", true),
+        ("contains(pair(1, 3), \"3\")", false),
+        ("contains(pair(false, nil), nil)", true),
+        ("contains(pair(pair(1, 2), 3), 1)", false),
+        ("contains(\"rslox\", \"lox\")", true),
+        ("contains(\"rslox\", \"Lox\")", false),
+        ("contains(\"rslox\", \"\")", true),
+        ("contains(\"\", \"a\")", false),
+    ] {
+        let code = format!("{pairs}{code}");
+        assert_eq!(eval(&code).unwrap(), Value::from(expected), "{code}");
+    }
+
+    assert!(matches!(
+        eval("contains(12, 1);"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("contains() expects an array or a string but got 'number'.")
+    ));
+    assert!(matches!(
+        eval("contains(\"123\", 1);"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("contains() can only find a string in a string, but got 'number'.")
+    ));
+    assert!(matches!(
+        eval("contains(\"a\");"),
+        Err(VMError::RuntimeError(_))
+    ));
+}
+
+#[test]
+fn test_split_native() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    for (code, expected) in [
+        (r#"split("a,b,c", ",")"#, "[a, b, c]"),
+        // Consecutive separators, and separators at the ends, give empty strings
+        (r#"split("a,,b,", ",")"#, "[a, , b, ]"),
+        (r#"split(",", ",")"#, "[, ]"),
+        (r#"split("a, b", ", ")"#, "[a, b]"),
+        (r#"split("abc", ";")"#, "[abc]"),
+        (r#"split("", ",")"#, "[]"),
+        (r#"split("héllo", "")"#, "[h, é, l, l, o]"),
+        (r#"split("", "")"#, "[]"),
+    ] {
+        assert_eq!(
+            eval(code).unwrap(),
+            Value::from(expected.to_owned()),
+            "{code}"
+        );
+    }
+    // Splitting an empty string gives one empty string, and an empty separator gives no characters
+    assert_eq!(eval(r#"split("", ",").length"#).unwrap(), Value::from(1.0));
+    assert_eq!(eval(r#"split("", "").length"#).unwrap(), Value::from(0.0));
+    // Elements are strings
+    assert_eq!(
+        eval(r#"var (a, b) = split("x-y", "-"); a + b"#).unwrap(),
+        Value::from("xy".to_owned())
+    );
+
+    assert!(matches!(
+        eval(r#"split("1,2", 1);"#),
+        Err(VMError::RuntimeError(e)) if e.starts_with("split() expects a string but got 'number'.")
+    ));
+    assert!(matches!(
+        eval(r#"split("1,2");"#),
+        Err(VMError::RuntimeError(_))
+    ));
+}
+
+#[test]
+fn test_join_native() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    let pairs = "fun pair(a, b) { return a, b; } ";
+    for (code, expected) in [
+        (r#"join(pair("a", "b"), "-")"#, "a-b"),
+        (r#"join(split("a,b,c", ","), ", ")"#, "a, b, c"),
+        (r#"join(split("a,,b", ","), "")"#, "ab"),
+        // Empty and single element arrays don't have a separator
+        (r#"join(split("", ""), "-")"#, ""),
+        (r#"join(split("only", ","), "-")"#, "only"),
+        // Elements which aren't strings are written like `print` writes them
+        (r#"join(pair(1.5, nil), " ")"#, "1.5 nil"),
+        (r#"join(pair(true, pair(1, 2)), "; ")"#, "true; [1, 2]"),
+    ] {
+        let code = format!("{pairs}{code}");
+        assert_eq!(
+            eval(&code).unwrap(),
+            Value::from(expected.to_owned()),
+            "{code}"
+        );
+    }
+
+    assert!(matches!(
+        eval(r#"join("a,b", ",");"#),
+        Err(VMError::RuntimeError(e)) if e.starts_with("join() expects an array but got 'string'.")
+    ));
+    assert!(matches!(
+        eval(r#"join(split("a", ","), nil);"#),
+        Err(VMError::RuntimeError(e)) if e.starts_with("join() expects a string but got 'nil'.")
+    ));
+}
+
+#[test]
+fn test_array_literals() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    let code = "[1, \"two\", [3, nil], 2 + 2]";
+    assert_eq!(
+        eval(code).unwrap(),
+        Value::from("[1, two, [3, nil], 4]".to_owned())
+    );
+    assert_eq!(eval("[]").unwrap(), Value::from("[]".to_owned()));
+    // Elements are evaluated in order
+    let code = "var log = \"\"; fun f(x) { log = log + x; return x; } [f(1), f(2), f(3)]; log";
+    assert_eq!(eval(code).unwrap(), Value::from("123".to_owned()));
+    // Literals work wherever arrays do
+    assert_eq!(
+        eval("var (x, y) = [10, 20]; x - y").unwrap(),
+        Value::from(-10.0)
+    );
+    assert_eq!(eval("([1] + [2, 3]).length").unwrap(), Value::from(3.0));
+    assert_eq!(eval("contains([1, 2], 2)").unwrap(), Value::from(true));
+
+    assert!(matches!(
+        eval("var (x, y) = [1];"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Expected 2 values to unpack but got 1.")
+    ));
+    assert!(matches!(
+        eval("var (x, y) = 1;"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Can only unpack an array.")
+    ));
+}
+
+#[test]
+fn test_array_indexing() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    let array = "var a = [10, 20, [30, 40]]; ";
+    for (index, expected) in [("0", 10.0), ("1", 20.0), ("2][1", 40.0), ("1 + 1][0", 30.0)] {
+        let code = format!("{array} a[{index}]");
+        assert_eq!(eval(&code).unwrap(), Value::from(expected), "{code}");
+    }
+    // Negative index counts from the end, down to `-length`, which is the first element
+    for (index, expected) in [("-1][-2", 30.0), ("-2", 20.0), ("-a.length", 10.0)] {
+        let code = format!("{array} a[{index}]");
+        assert_eq!(eval(&code).unwrap(), Value::from(expected), "{code}");
+    }
+    assert_eq!(
+        eval("fun pair() { return 1, 2; } pair()[-1]").unwrap(),
+        Value::from(2.0)
+    );
+
+    for (index, message) in [
+        ("3", "Index 3 is out of range for array of length 3."),
+        ("-4", "Index -4 is out of range for array of length 3."),
+        ("0.5", "Array index should be a whole number but got '0.5'."),
+        (
+            "\"0\"",
+            "Array index should be a whole number but got '\"0\"'.",
+        ),
+    ] {
+        let code = format!("{array} a[{index}];");
+        assert!(
+            matches!(eval(&code), Err(VMError::RuntimeError(e)) if e.starts_with(message)),
+            "{code}"
+        );
+    }
+    assert!(matches!(
+        eval("[][-1];"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Index -1 is out of range for array of length 0.")
+    ));
+    assert!(matches!(
+        eval("\"abc\"[0];"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Only arrays can be indexed, 'string' can't.")
+    ));
+    // Elements can't be assigned, arrays don't change after they're built
+    assert!(matches!(
+        eval("var a = [1]; a[0] = 2;"),
+        Err(VMError::CompileError(e)) if e.to_string().contains("Invalid assignment target.")
+    ));
+}
+
+#[test]
+fn test_global_statement() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    // Local `x` shadows the global one, `global x` writes the outer one
+    let code = "var x = 1; fun f() { var x = 2; global x = x + 10; return x; } var local = f(); format(\"{} {}\", x, local)";
+    assert_eq!(eval(code).unwrap(), Value::from("12 2".to_owned()));
+    // Works from nested functions, and in blocks at top level
+    let code = "var x = 0; fun outer() { var x = 1; fun inner() { var x = 2; global x = 3; } inner(); return x; } var result = outer(); { var x = 5; global x = x + result; } x";
+    assert_eq!(eval(code).unwrap(), Value::from(6.0));
+
+    assert!(matches!(
+        eval("fun f() { global missing = 1; } f();"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Undefined variable 'missing'")
+    ));
+}
+
+#[test]
+fn test_hoisted_function_declarations() {
+    use crate::{
+        compiler::{CompilationContext, CompilerState, types::FunctionType},
+        vm::{VM, errors::VMError},
+    };
+
+    let run = |code: &str, hoist: bool| {
+        let mut context = CompilationContext::new(code);
+        context.set_hoist_functions(hoist);
+        context.push(CompilerState::new(FunctionType::default_script()));
+        let function = context.compile().map_err(VMError::CompileError)?;
+        let mut vm = VM::new();
+        let result = vm
+            .run_isolated(function.clone())
+            .map(|value| value.to_string());
+        vm.free_script(function);
+        vm.reset_vm();
+        result
+    };
+
+    // Mutually recursive functions, both called before they're declared
+    let code = r#"
+var result = format("{} {}", is_even(10), is_odd(7));
+fun is_even(n) { if (n == 0) return true; return is_odd(n - 1); }
+fun is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+result
+"#;
+    assert_eq!(run(code, true).unwrap(), "true true");
+    assert!(matches!(
+        run(code, false),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Undefined variable 'is_even'")
+    ));
+
+    // Only top-level declarations are hoisted, and each is compiled once
+    let code = r#"
+var calls = 0;
+fun outer() { fun inner() { return 1; } calls = calls + 1; return inner(); }
+var first = outer();
+{ fun local() { return 2; } first = first + local(); }
+if (true) { calls = calls + 1; }
+fun after() { return [1, 2]; }
+format("{} {} {}", first, calls, after())
+"#;
+    assert_eq!(run(code, true).unwrap(), "3 2 [1, 2]");
+    assert_eq!(run(code, false).unwrap(), "3 2 [1, 2]");
+    // Functions in blocks still have to be declared before they're called
+    assert!(run("{ local(); fun local() {} }", true).is_err());
+
+    let error = run("f(); fun f( {}", true).unwrap_err().to_string();
+    assert!(error.contains("Expect"), "{error}");
+}
+
+#[test]
+fn test_chained_comparisons() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    for (code, expected) in [
+        ("1 < 2 < 3", true),
+        ("3 > 2 > 1", true),
+        ("1 < 3 < 2", false),
+        ("3 < 1 < 2", false),
+        ("1 <= 1 < 2 >= 0", true),
+        ("1 <= 1 < 2 >= 3", false),
+        // `==` has lower precedence, so it compares the result of the chain
+        ("1 < 2 < 3 == true", true),
+        ("0 < 0/0 < 1", false),
+    ] {
+        assert_eq!(eval(code).unwrap(), Value::from(expected), "{code}");
+    }
+
+    // Middle operand is evaluated once, and chain stops at first false comparison
+    let code = r#"var log = ""; fun f(x) { log = log + x; return x; } var result = f(1) < f(2) < f(0) < f(3); format("{} {}", result, log)"#;
+    assert_eq!(eval(code).unwrap(), Value::from("false 120".to_owned()));
+    assert_eq!(eval("2 < 1 < nil").unwrap(), Value::from(false));
+    assert!(matches!(
+        eval("1 < 2 < nil"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Invalid operation on operands of type 'number' and 'nil'.")
+    ));
+}
+
+#[test]
+fn test_function_expressions() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    let code = "var add = fun (a, b) { return a + b; }; add(1, 2)";
+    assert_eq!(eval(code).unwrap(), Value::from(3.0));
+    // Passed as an argument, and called right away
+    let code = "fun apply(f, x) { return f(x); } apply(fun (x) { return x * x; }, 5) + (fun () { return 1; })()";
+    assert_eq!(eval(code).unwrap(), Value::from(26.0));
+    // Name of a function expression doesn't declare a variable
+    assert_eq!(
+        eval("var f = fun twice(x) { return x * 2; }; format(\"{} {}\", f, f(2))").unwrap(),
+        Value::from("<fn twice> 4".to_owned())
+    );
+    assert!(matches!(
+        eval("var f = fun twice(x) {}; twice(1);"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Undefined variable 'twice'")
+    ));
+    // Declared functions and function expressions are called the same way
+    let code = "fun declared(x) { return x + 1; } var expression = fun (x) { return x + 1; }; declared(1) == expression(1)";
+    assert_eq!(eval(code).unwrap(), Value::from(true));
+
+    // At the start of a statement, `fun` is a declaration, so an anonymous function has to be wrapped in parentheses
+    assert!(matches!(eval("fun () {}"), Err(VMError::CompileError(_))));
+    assert!(matches!(
+        eval("var f = fun (a) {}; f();"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Expected 1 arguments but got 0 when calling <anonymous fn>.")
+    ));
+    assert!(matches!(
+        eval("var f = fun () { return nil + 1; }; f();"),
+        Err(VMError::RuntimeError(e)) if e.contains("[line 1] in <anonymous fn>\n[line 1] in <script>")
+    ));
+}
+
+#[test]
+fn test_capture_natives() {
+    use crate::{eval, value::Value, vm::errors::VMError};
+
+    // Output of a loop is collected into a string
+    let code = r#"
+capture_begin();
+for (var i = 0; i < 3; i = i + 1) {
+    print i;
+    println(";");
+}
+capture_end()
+"#;
+    assert_eq!(eval(code).unwrap(), Value::from("0;\n1;\n2;\n".to_owned()));
+
+    // Captures nest, inner one doesn't add its output to outer one
+    let code = r#"capture_begin(); print "a"; capture_begin(); print "b"; var inner = capture_end(); print "c"; capture_end() + inner"#;
+    assert_eq!(eval(code).unwrap(), Value::from("acb".to_owned()));
+    assert_eq!(
+        eval("capture_begin(); capture_end()").unwrap(),
+        Value::from(String::new())
+    );
+
+    assert!(matches!(
+        eval("capture_end();"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("capture_end() called without capture_begin().")
+    ));
+    assert!(matches!(
+        eval("capture_begin(1);"),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Expected 0 arguments but got 1 when calling 'capture_begin'.")
+    ));
+}
+
+#[test]
+fn test_parameter_type_annotations() {
+    use crate::{
+        eval, interpret,
+        value::Value,
+        vm::{VM, errors::VMError},
+    };
+
+    let area = "fun area(width: number, height: number) { return width * height; } ";
+    assert_eq!(
+        eval(&format!("{area} area(2, 3)")).unwrap(),
+        Value::from(6.0)
+    );
+    // Passing a string where a number is expected fails before the body runs
+    let code = format!("{area} area(2, \"3\");");
+    assert!(
+        matches!(eval(&code), Err(VMError::RuntimeError(e)) if e.starts_with("Expected argument 2 to be 'number' but got 'string'.\n[line 1] in area()")),
+        "{code}"
+    );
+    // Body never ran, globals survive the failed script on the same VM
+    let mut vm = VM::new();
+    interpret(
+        "var calls = 0; fun f(x: number) { calls = calls + 1; }",
+        &mut vm,
+    )
+    .unwrap();
+    assert!(matches!(
+        interpret("f(\"1\");", &mut vm),
+        Err(VMError::RuntimeError(_))
+    ));
+    assert_eq!(interpret("calls", &mut vm).unwrap(), Value::from(0.0));
+    // Body does run with an argument of the right type
+    interpret("f(1);", &mut vm).unwrap();
+    assert_eq!(interpret("calls", &mut vm).unwrap(), Value::from(1.0));
+    vm.reset_vm();
+
+    // Other types, and natives passed as functions
+    let code = r#"
+fun check(b: bool, s: string, f: function, a: array, any) { return f(s); }
+check(true, "ok", len, [1], nil) + check(false, "", fun (x: string) { return 1; }, [], 1)
+"#;
+    assert_eq!(eval(code).unwrap(), Value::from(3.0));
+    let code = "var twice = fun (s: string) { return s + s; }; twice(1);";
+    assert!(matches!(
+        eval(code),
+        Err(VMError::RuntimeError(e)) if e.starts_with("Expected argument 1 to be 'string' but got 'number'.\n[line 1] in <anonymous fn>")
+    ));
+}
@@ -59,6 +59,7 @@ impl From<Value> for String {
                     ObjectType::String(s) => *s,
                     ObjectType::Function(f) => format!("{f}"),
                     ObjectType::Native(_f) => "<native>".to_string(),
+                    array @ ObjectType::Array(_) => array.to_string(),
                 }
             },
             Value::Literal(_) => format!("{val}"),
@@ -1,6 +1,8 @@
 mod conversions;
 pub mod objects;
 mod operators;
+#[cfg(test)]
+mod tests;
 
 use std::ptr::NonNull;
 
@@ -22,6 +24,30 @@ pub enum Literal {
     String(String),
 }
 
+/// How numbers are written when values are printed, chosen by `VM::number_format`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NumberFormat {
+    /// Shortest form which reads back as the same number, like `0.1` or `42`
+    #[default]
+    Default,
+    /// Fixed number of digits after the decimal point, e.g. `Fixed(2)` writes `1.5` as `1.50`
+    Fixed(u8),
+    /// Scientific notation, like `1.5e3`
+    Scientific,
+}
+
+impl NumberFormat {
+    /// Writes a number in this format
+    #[must_use]
+    pub fn format(self, number: f64) -> String {
+        match self {
+            Self::Default => format!("{number}"),
+            Self::Fixed(digits) => format!("{number:.*}", digits as usize),
+            Self::Scientific => format!("{number:e}"),
+        }
+    }
+}
+
 /// Represents supported types and their values.
 /// Since value can be of only a single type, enum is enough for now.
 /// Each variant will take 16 bytes in memory, like boolean will also take 16 bytes
@@ -32,13 +58,27 @@ pub enum Literal {
 /// it's a waste of memory. If we want to optimize in such a way that a boolean should take
 /// 1 byte, we've to re-think how to represent Value internally. It will make code much more
 /// complex and requires a careful design.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum Value {
     Literal(Literal),
     /// Stores pointer to the object stored on heap
     Obj(NonNull<Object>),
 }
 
+/// Cloning is shallow, it's same as `shallow_copy`. Cloning an `Obj` copies the pointer, so both values alias the same object.
+///
+/// Objects created at runtime are owned by the `VM`, through `vm.objects` list, and not by any of its aliases. An alias must never
+/// free the object or move its contents out, e.g. with `as_string`, otherwise other aliases and the list will point to freed memory.
+/// Use `deep_copy` if an independent object is needed.
+impl Clone for Value {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Literal(literal) => Self::Literal(literal.clone()),
+            Self::Obj(pointer) => Self::Obj(*pointer),
+        }
+    }
+}
+
 impl Value {
     /// Creates a `Value` object from the `String`. Since it's created at runtime, it'll have `Obj` variant
     /// # Errors
@@ -66,12 +106,107 @@ impl Value {
         Ok(Self::Obj(obj_pointer))
     }
 
+    /// Creates a `Value` object from the list of values. Since it's created at runtime, it'll have `Obj` variant
+    /// # Errors
+    ///
+    /// Returns a `VMError` when `ObjectPointer` creation fails
+    pub fn from_runtime_array(values: Vec<Value>, vm: &mut VM) -> Result<Value, VMError> {
+        let obj_pointer = Object::from_array(values, vm)?;
+        Ok(Self::Obj(obj_pointer))
+    }
+
+    /// Returns a copy of the value which aliases the same object, if it's an `Obj`. See `Clone` implementation for aliasing rules.
+    #[must_use]
+    pub fn shallow_copy(&self) -> Value {
+        self.clone()
+    }
+
+    /// Returns a copy of the value which doesn't alias the original. Strings and arrays are copied into new objects tracked by the `VM`,
+    /// and array elements are copied recursively. Functions can't be modified, so they're shared.
+    /// # Errors
+    ///
+    /// Returns a `VMError` when `ObjectPointer` creation fails
+    pub fn deep_copy(&self, vm: &mut VM) -> Result<Value, VMError> {
+        if self.is_object_string() {
+            Value::from_runtime_str(self.as_string_ref().to_owned(), vm)
+        } else if self.is_array() {
+            let values = self
+                .as_array_ref()
+                .iter()
+                .map(|value| value.deep_copy(vm))
+                .collect::<Result<Vec<_>, _>>()?;
+            Value::from_runtime_array(values, vm)
+        } else {
+            Ok(self.clone())
+        }
+    }
+
     /// Used to generate constant default/Nil value.
     #[must_use]
     pub const fn new_nil() -> Value {
         Value::Literal(Literal::Nil)
     }
 
+    /// Creates a number value.
+    ///
+    /// ```
+    /// use rslox::value::Value;
+    ///
+    /// let value = Value::number(-2.5);
+    /// assert!(value.is_number());
+    /// assert_eq!(value.to_number(), -2.5);
+    /// ```
+    #[must_use]
+    pub const fn number(value: f64) -> Value {
+        Value::Literal(Literal::Number(value))
+    }
+
+    /// Creates a boolean value.
+    ///
+    /// ```
+    /// use rslox::value::Value;
+    ///
+    /// assert!(Value::boolean(false).is_falsey());
+    /// assert!(!Value::boolean(true).is_falsey());
+    /// ```
+    #[must_use]
+    pub const fn boolean(value: bool) -> Value {
+        Value::Literal(Literal::Bool(value))
+    }
+
+    /// Creates the `nil` value. Same as `new_nil`.
+    ///
+    /// ```
+    /// use rslox::value::Value;
+    ///
+    /// assert!(Value::nil().is_nil());
+    /// ```
+    #[must_use]
+    pub const fn nil() -> Value {
+        Self::new_nil()
+    }
+
+    /// Creates a string owned by the `VM`, like strings created by a running script. It's freed by `reset_vm`, so it shouldn't be used after that.
+    ///
+    /// ```
+    /// use rslox::{interpret, value::Value, vm::VM};
+    ///
+    /// let mut vm = VM::new();
+    /// let greeting = Value::string(&mut vm, "Hello").unwrap();
+    /// vm.set_global("greeting", greeting);
+    /// vm.set_global("count", Value::number(3.0));
+    ///
+    /// let result = interpret(r#"greeting + " " + count"#, &mut vm).unwrap();
+    /// assert_eq!(result.as_string_ref(), "Hello 3");
+    /// vm.reset_vm();
+    /// ```
+    /// # Errors
+    ///
+    /// Returns a `VMError` when `ObjectPointer` creation fails
+    pub fn string(vm: &mut VM, value: &str) -> Result<Value, VMError> {
+        Value::from_runtime_str(value.to_owned(), vm)
+    }
+
     /// If value is pf boolean type, returns true
     #[must_use]
     pub fn is_bool(&self) -> bool {
@@ -130,6 +265,147 @@ impl Value {
         }
     }
 
+    /// Returns the reference to the inner string, whether it's a literal or created at runtime.
+    #[must_use]
+    pub fn as_string_ref(&self) -> &str {
+        match self {
+            Self::Literal(Literal::String(s)) => s,
+            Self::Obj(obj) => unsafe {
+                match &obj.as_ref().ty {
+                    ObjectType::String(s) => s,
+                    _ => unreachable!(),
+                }
+            },
+            Self::Literal(_) => unreachable!(),
+        }
+    }
+
+    /// Compares two values by their contents. Strings are equal when they have same characters, no matter if one is a literal and other is created at runtime. Other objects, like functions, are only equal to themselves.
+    #[must_use]
+    pub fn values_equal(&self, other: &Value) -> bool {
+        if self.is_string() && other.is_string() {
+            return self.as_string_ref() == other.as_string_ref();
+        }
+
+        match (self, other) {
+            (Self::Literal(a), Self::Literal(b)) => a == b,
+            // Compares raw pointers, so object is only equal to itself
+            (Self::Obj(a), Self::Obj(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Compares like `values_equal`, but a number is also equal to a string containing the same number, so `1 == " 1.0 "` is true.
+    /// String is trimmed before parsing, and it should contain a finite number, so `"inf"` or `"abc"` never equal a number.
+    #[must_use]
+    pub fn loose_equals(&self, other: &Value) -> bool {
+        // Returns the number a string holds, if any
+        let numeric = |value: &Value| {
+            value
+                .as_string_ref()
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+        };
+
+        match (self, other) {
+            (Self::Literal(Literal::Number(number)), string) if string.is_string() => {
+                numeric(string) == Some(*number)
+            }
+            (string, Self::Literal(Literal::Number(number))) if string.is_string() => {
+                numeric(string) == Some(*number)
+            }
+            _ => self.values_equal(other),
+        }
+    }
+
+    /// Returns a representation of the value for debugging, which is different from how `print` shows it.
+    /// Strings are quoted, so `"1"` can be told apart from `1`, and functions show their arity, like `<fn add/2>`.
+    #[must_use]
+    pub fn debug_repr(&self) -> String {
+        match self {
+            Self::Literal(Literal::String(s)) => format!("{s:?}"),
+            Self::Literal(_) => self.to_string(),
+            Self::Obj(obj) => unsafe {
+                match &obj.as_ref().ty {
+                    ObjectType::String(s) => format!("{s:?}"),
+                    ObjectType::Function(function) => match &function.name {
+                        Some(name) => format!("<fn {name}/{}>", function.arity),
+                        None => format!("<anonymous fn/{}>", function.arity),
+                    },
+                    ObjectType::Native(_) => "<native fn>".to_owned(),
+                    ObjectType::Array(values) => {
+                        Value::format_array(values, &mut Vec::new(), &Value::debug_repr)
+                    }
+                }
+            },
+        }
+    }
+
+    /// Returns the value as `print` writes it, with numbers, including ones in arrays, written in `number_format`
+    #[must_use]
+    pub fn format_with(&self, number_format: NumberFormat) -> String {
+        match self {
+            Self::Literal(Literal::Number(number)) => number_format.format(*number),
+            _ if self.is_array() => {
+                Self::format_array(self.as_array_ref(), &mut Vec::new(), &|value| {
+                    value.format_with(number_format)
+                })
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// Formats elements of an array, like `[1, [2, 3]]`, using `element` for values which aren't arrays.
+    /// `path` has arrays being formatted, from outermost one. An array nested in itself is shown as `[...]`, instead of recursing forever.
+    pub(super) fn format_array(
+        values: &[Value],
+        path: &mut Vec<*const Value>,
+        element: &dyn Fn(&Value) -> String,
+    ) -> String {
+        // Empty arrays don't have elements to recurse into. Pointers of empty slices aren't unique either.
+        if values.is_empty() {
+            return "[]".to_owned();
+        }
+        if path.contains(&values.as_ptr()) {
+            return "[...]".to_owned();
+        }
+
+        path.push(values.as_ptr());
+        let elements: Vec<String> = values
+            .iter()
+            .map(|value| {
+                if value.is_array() {
+                    Self::format_array(value.as_array_ref(), path, element)
+                } else {
+                    element(value)
+                }
+            })
+            .collect();
+        path.pop();
+        format!("[{}]", elements.join(", "))
+    }
+
+    /// Returns name of the type of the value, to be shown to the user, e.g. in error messages
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Literal(Literal::Bool(_)) => "bool",
+            Self::Literal(Literal::Nil) => "nil",
+            Self::Literal(Literal::Number(_)) => "number",
+            Self::Literal(Literal::String(_)) => "string",
+            Self::Obj(obj) => unsafe {
+                match &obj.as_ref().ty {
+                    ObjectType::String(_) => "string",
+                    ObjectType::Function(_) => "function",
+                    ObjectType::Native(_) => "native function",
+                    ObjectType::Array(_) => "array",
+                }
+            },
+        }
+    }
+
     /// Returns the reference to the function object
     #[must_use]
     pub fn as_function_ref(&self) -> &FunctionObject {
@@ -157,6 +433,20 @@ impl Value {
         }
     }
 
+    /// Returns the reference to the elements of the array
+    #[must_use]
+    pub fn as_array_ref(&self) -> &[Value] {
+        match self {
+            Self::Obj(obj) => unsafe {
+                match &obj.as_ref().ty {
+                    ObjectType::Array(values) => values,
+                    _ => unreachable!(),
+                }
+            },
+            Self::Literal(_) => unreachable!(),
+        }
+    }
+
     /// Returns the reference to the native object
     #[must_use]
     pub fn as_native_ref(&self) -> &NativeFn {
@@ -232,6 +522,14 @@ impl Value {
         }
     }
 
+    /// Checks if the value is an array, which is always created at runtime
+    #[must_use]
+    pub fn is_array(&self) -> bool {
+        unsafe {
+            matches!(self, Self::Obj(obj) if matches!((obj.as_ref()).ty, ObjectType::Array(_)))
+        }
+    }
+
     /// Checks if `Value` is a string
     #[must_use]
     pub fn is_string(&self) -> bool {
@@ -253,7 +551,6 @@ impl std::fmt::Display for Value {
                 write!(f, "{n}")
             }
             Self::Literal(Literal::String(s)) => {
-                let s = s.replace("\\n", "\n");
                 write!(f, "{s}")
             }
             Self::Obj(obj) => unsafe { write!(f, "{}", obj.as_ref()) },
@@ -2,19 +2,24 @@ use std::{fmt::Display, ptr::NonNull};
 
 use crate::{
     chunk::Chunk,
+    debug::Debug,
     value::Value,
     vm::{VM, errors::VMError},
 };
 
-pub type NativeFn = fn(arg_count: u8, args: Vec<Value>) -> Value;
+/// Function implemented in Rust and callable from Lox. Receives the `VM`, e.g. to write output, and arguments in the order they were passed.
+pub type NativeFn = fn(vm: &mut VM, args: Vec<Value>) -> Result<Value, VMError>;
 
-#[derive(Debug, Clone, PartialEq)]
-/// Type to store object types and associated data
+/// Type to store object types and associated data.
+/// Doesn't implement `PartialEq`, objects are compared by identity, see `Value::values_equal`
+#[derive(Debug, Clone)]
 pub enum ObjectType {
     /// Stores owned pointer to the String allocated on heap
     String(Box<String>),
     Function(Box<FunctionObject>),
     Native(Box<NativeFn>),
+    /// Stores the list of values, like an array literal or multiple values returned from a function
+    Array(Box<Vec<Value>>),
 }
 
 /// `Display` trait implementation to display `ObjectType`s nicely
@@ -22,7 +27,6 @@ impl std::fmt::Display for ObjectType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::String(s) => {
-                let s = s.replace("\\n", "\n");
                 write!(f, "{s}")
             }
             Self::Function(fun) => {
@@ -31,11 +35,17 @@ impl std::fmt::Display for ObjectType {
             Self::Native(_fun) => {
                 write!(f, "<native>")
             }
+            Self::Array(values) => {
+                let array = Value::format_array(values, &mut Vec::new(), &Value::to_string);
+                write!(f, "{array}")
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Doesn't implement `PartialEq`, because two different functions with same bytecode shouldn't be equal.
+/// Function values are compared by identity instead.
+#[derive(Debug, Clone)]
 pub struct FunctionObject {
     pub arity: u8,
     pub chunk: Chunk,
@@ -47,7 +57,8 @@ impl Display for FunctionObject {
         if let Some(n) = self.name.as_ref() {
             write!(f, "<fn {n}>")
         } else {
-            write!(f, "<script>")
+            // Top-level script isn't a value scripts can get hold of, so a function without a name is an anonymous one
+            write!(f, "<anonymous fn>")
         }
     }
 }
@@ -71,6 +82,37 @@ impl FunctionObject {
     fn init_chunk() -> Chunk {
         Chunk::default()
     }
+
+    /// Returns the disassembled bytecode of the function. Useful for tooling, like static analysis, and tests.
+    #[must_use]
+    pub fn disassemble(&self) -> String {
+        let name = self.name.as_deref().unwrap_or("<script>");
+        Debug::chunk_to_string(&self.chunk, name)
+    }
+
+    /// Same as `disassemble`, but also disassembles functions declared inside this function, which are stored as constants
+    #[must_use]
+    pub fn disassemble_all(&self) -> String {
+        self.disassemble_all_with("<script>")
+    }
+
+    /// Disassembles the function and functions nested in it. `unnamed` is shown if the function has no name, nested functions
+    /// without a name are anonymous ones.
+    fn disassemble_all_with(&self, unnamed: &str) -> String {
+        let name = self.name.as_deref().unwrap_or(unnamed);
+        let mut out = Debug::chunk_to_string(&self.chunk, name);
+        for constant in &self.chunk.constants {
+            if constant.is_function() {
+                out.push('\n');
+                out.push_str(
+                    &constant
+                        .as_function_ref()
+                        .disassemble_all_with("<anonymous fn>"),
+                );
+            }
+        }
+        out
+    }
 }
 
 /// Type to store a raw pointer to `Object` stored on heap. `NonNull` ensures that raw pointer is not null and also is space efficient.
@@ -79,7 +121,7 @@ pub type ObjectPointer = NonNull<Object>;
 /// Type to store reference to the Object for garbage collection
 pub type ObjectNode = Option<ObjectPointer>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 /// Data structure to store the `ObjectType` (which owns the value) and `next` node, for garbage collection
 pub struct Object {
     /// Stores the type of the `Object` being created
@@ -148,6 +190,14 @@ impl Object {
         Self::with_vm(ObjectType::Function(Box::new(fun_obj)), vm)
     }
 
+    /// Creates `Object` of type `Array` at runtime.
+    /// # Errors
+    ///
+    /// Returns an `Err` when `ObjectPointer` creation fails
+    pub fn from_array(values: Vec<Value>, vm: &mut VM) -> Result<ObjectPointer, VMError> {
+        Self::with_vm(ObjectType::Array(Box::new(values)), vm)
+    }
+
     /// Creates `Object` of type `FunctionObject` at runtime.
     /// # Errors
     ///
@@ -5,6 +5,10 @@ use crate::value::Value;
 /// Overrides '+' operator for custom type `Value`
 /// It's like operator overloading in C++
 /// Only works if `Value` is of type number
+///
+/// # Panics
+///
+/// Panics if any operand isn't a number. Types should be checked before, to return a proper error.
 impl Add for Value {
     type Output = self::Value;
     fn add(self, rhs: Self) -> Self::Output {
@@ -16,15 +20,18 @@ impl Add for Value {
             return (a + b).into();
         }
 
-        // This should be unreachable, types should be checked in compiler for proper
-        // error handling
-        unreachable!()
+        // VM checks types of operands in `binary_op` and returns a runtime error, before using the operator
+        unreachable!("'+' operator is only defined for numbers")
     }
 }
 
 /// Overrides '-' operator for custom type `Value`
 /// It's like operator overloading in C++
 /// Only works if `Value` is of type number
+///
+/// # Panics
+///
+/// Panics if any operand isn't a number. Types should be checked before, to return a proper error.
 impl Sub for Value {
     type Output = self::Value;
     fn sub(self, rhs: Self) -> Self::Output {
@@ -34,15 +41,18 @@ impl Sub for Value {
             return (a - b).into();
         }
 
-        // This should be unreachable, types should be checked in compiler for proper
-        // error handling
-        unreachable!()
+        // VM checks types of operands in `binary_op` and returns a runtime error, before using the operator
+        unreachable!("'-' operator is only defined for numbers")
     }
 }
 
 /// Overrides '*' operator for custom type `Value`
 /// It's like operator overloading in C++
 /// Only works if `Value` is of type number
+///
+/// # Panics
+///
+/// Panics if any operand isn't a number. Types should be checked before, to return a proper error.
 impl Mul for Value {
     type Output = self::Value;
     fn mul(self, rhs: Self) -> Self::Output {
@@ -52,15 +62,18 @@ impl Mul for Value {
             return (a * b).into();
         }
 
-        // This should be unreachable, types should be checked in compiler for proper
-        // error handling
-        unreachable!()
+        // VM checks types of operands in `binary_op` and returns a runtime error, before using the operator
+        unreachable!("'*' operator is only defined for numbers")
     }
 }
 
 /// Overrides '/' operator for custom type `Value`
 /// It's like operator overloading in C++
 /// Only works if `Value` is of type number
+///
+/// # Panics
+///
+/// Panics if any operand isn't a number. Types should be checked before, to return a proper error.
 impl Div for Value {
     type Output = self::Value;
     fn div(self, rhs: Self) -> Self::Output {
@@ -70,15 +83,18 @@ impl Div for Value {
             return (a / b).into();
         }
 
-        // This should be unreachable, types should be checked in compiler for proper
-        // error handling
-        unreachable!()
+        // VM checks types of operands in `binary_op` and returns a runtime error, before using the operator
+        unreachable!("'/' operator is only defined for numbers")
     }
 }
 
 /// Overrides '-' (negation, which is unary) operator, for custom type `Value`
 /// It's like operator overloading in C++
 /// Only works if `Value` is of type number
+///
+/// # Panics
+///
+/// Panics if operand isn't a number. Type should be checked before, to return a proper error.
 impl Neg for Value {
     type Output = self::Value;
 
@@ -87,21 +103,24 @@ impl Neg for Value {
             let a: f64 = self.into();
             return (-a).into();
         }
-        // This code shouldn't be reached
-        unreachable!()
+        // VM checks type of the operand in `op_negate` and returns a runtime error, before using the operator
+        unreachable!("Unary '-' operator is only defined for numbers")
     }
 }
-/// Overrides '!' operator for custom type `Value`, only works if value is Bool
+/// Overrides '!' operator for custom type `Value`, only works if value is Bool or Nil
 /// It's like operator overloading in C++
+///
+/// # Panics
+///
+/// Panics if operand is any other type.
 impl Not for Value {
     type Output = self::Value;
     fn not(self) -> Self::Output {
-        if self.is_bool() {
-            let b: bool = self.into();
-            return (!b).into();
+        // Same as `op_not` in the VM, which uses `is_falsey` and doesn't call this operator
+        if self.is_bool() || self.is_nil() {
+            return self.is_falsey().into();
         }
 
-        // This code shouldn't be reached
-        unreachable!();
+        unreachable!("'!' operator is only defined for bool and nil");
     }
 }
@@ -0,0 +1,253 @@
+use crate::{value::Value, vm::VM};
+
+#[test]
+fn literal_and_runtime_strings_are_equal() {
+    let mut vm = VM::new();
+    let literal = Value::from("ab".to_owned());
+    let runtime = Value::from_runtime_str("ab".to_owned(), &mut vm).unwrap();
+
+    assert!(literal.values_equal(&runtime));
+    assert!(runtime.values_equal(&literal));
+
+    vm.reset_vm();
+}
+
+#[test]
+fn runtime_strings_are_compared_by_content() {
+    let mut vm = VM::new();
+    let first = Value::from_runtime_str("ab".to_owned(), &mut vm).unwrap();
+    let second = Value::from_runtime_str("ab".to_owned(), &mut vm).unwrap();
+    let different = Value::from_runtime_str("abc".to_owned(), &mut vm).unwrap();
+
+    // Two separate allocations, but same content
+    assert_ne!(first, second);
+    assert!(first.values_equal(&second));
+    assert!(!first.values_equal(&different));
+
+    vm.reset_vm();
+}
+
+#[test]
+fn literals_are_compared_by_value() {
+    assert!(Value::from(1.0).values_equal(&Value::from(1.0)));
+    assert!(!Value::from(1.0).values_equal(&Value::from(2.0)));
+    assert!(Value::from(true).values_equal(&Value::from(true)));
+    assert!(Value::new_nil().values_equal(&Value::new_nil()));
+    assert!(!Value::new_nil().values_equal(&Value::from(false)));
+    // Different types are never equal
+    assert!(!Value::from(1.0).values_equal(&Value::from("1".to_owned())));
+}
+
+#[test]
+fn functions_are_compared_by_identity() {
+    use crate::value::objects::FunctionObject;
+
+    // Both functions have same (empty) bytecode
+    let function: Value = FunctionObject::new().into();
+    let other: Value = FunctionObject::new().into();
+
+    assert!(function.values_equal(&function.clone()));
+    assert!(!function.values_equal(&other));
+}
+
+#[test]
+fn shallow_copy_aliases_object() {
+    let mut vm = VM::new();
+    let value = Value::from_runtime_str("ab".to_owned(), &mut vm).unwrap();
+
+    assert_eq!(value.shallow_copy(), value);
+
+    vm.reset_vm();
+}
+
+#[test]
+fn deep_copy_creates_tracked_objects() {
+    let mut vm = VM::new();
+    let string = Value::from_runtime_str("ab".to_owned(), &mut vm).unwrap();
+    let array = Value::from_runtime_array(vec![string.clone(), Value::from(1.0)], &mut vm).unwrap();
+
+    let copy = array.deep_copy(&mut vm).unwrap();
+
+    assert_ne!(copy, array);
+    assert_ne!(copy.as_array_ref()[0], string);
+    assert!(copy.as_array_ref()[0].values_equal(&string));
+    assert_eq!(copy.as_array_ref()[1], Value::from(1.0));
+    // Original objects and copies are all freed by the VM
+    assert_eq!(vm.tracked_objects().len(), 4);
+
+    vm.reset_vm();
+}
+
+#[test]
+fn type_names() {
+    let mut vm = VM::new();
+    let runtime = Value::from_runtime_str("ab".to_owned(), &mut vm).unwrap();
+
+    assert_eq!(Value::from(1.0).type_name(), "number");
+    assert_eq!(Value::from(true).type_name(), "bool");
+    assert_eq!(Value::new_nil().type_name(), "nil");
+    assert_eq!(Value::from("ab".to_owned()).type_name(), "string");
+    assert_eq!(runtime.type_name(), "string");
+
+    vm.reset_vm();
+}
+
+#[test]
+fn number_display() {
+    let display = |n: f64| Value::from(n).to_string();
+
+    // Integers don't show fractional part
+    assert_eq!(display(2.0), "2");
+    assert_eq!(display(-10.0), "-10");
+    assert_eq!(display(4.0 / 2.0), "2");
+    // Fractions show only required digits
+    assert_eq!(display(10.0 / 4.0), "2.5");
+    assert_eq!(display(0.1 + 0.2), "0.30000000000000004");
+    // Very large and very small numbers are never shown in scientific notation
+    assert_eq!(display(1e21), "1000000000000000000000");
+    assert_eq!(display(1.5e-7), "0.00000015");
+    // Special values
+    assert_eq!(display(f64::INFINITY), "inf");
+    assert_eq!(display(f64::NEG_INFINITY), "-inf");
+    assert_eq!(display(f64::NAN), "NaN");
+}
+
+#[test]
+fn debug_repr() {
+    use crate::value::objects::FunctionObject;
+
+    let mut vm = VM::new();
+    let runtime = Value::from_runtime_str("say \"hi\"".to_owned(), &mut vm).unwrap();
+    let mut function = FunctionObject::new();
+    function.name = Some("add".to_owned());
+    function.arity = 2;
+    let function: Value = function.into();
+    let array = Value::from_runtime_array(
+        vec![
+            Value::from(1.0),
+            Value::from("1".to_owned()),
+            Value::new_nil(),
+        ],
+        &mut vm,
+    )
+    .unwrap();
+
+    assert_eq!(Value::from("hi".to_owned()).debug_repr(), "\"hi\"");
+    assert_eq!(runtime.debug_repr(), r#""say \"hi\"""#);
+    assert_eq!(Value::from(2.5).debug_repr(), "2.5");
+    assert_eq!(Value::from(true).debug_repr(), "true");
+    assert_eq!(Value::new_nil().debug_repr(), "nil");
+    assert_eq!(function.debug_repr(), "<fn add/2>");
+    assert_eq!(array.debug_repr(), r#"[1, "1", nil]"#);
+    // `Display` is unchanged
+    assert_eq!(runtime.to_string(), "say \"hi\"");
+
+    vm.reset_vm();
+}
+
+#[test]
+fn not_operator() {
+    assert_eq!(!Value::from(true), Value::from(false));
+    assert_eq!(!Value::from(false), Value::from(true));
+    assert_eq!(!Value::new_nil(), Value::from(true));
+}
+
+#[test]
+#[should_panic(expected = "'+' operator is only defined for numbers")]
+fn arithmetic_operator_panics_on_non_numbers() {
+    let _ = Value::new_nil() + Value::from(1.0);
+}
+
+#[test]
+fn nested_arrays_display() {
+    use crate::eval;
+
+    let code = r#"fun pair(a, b) { return a, b; } pair(1, pair("two", pair(3, nil)))"#;
+    let result = eval(code).unwrap();
+    assert_eq!(result.to_string(), "[1, [two, [3, nil]]]");
+
+    let mut vm = VM::new();
+    let inner = Value::from_runtime_array(vec![Value::from("a".to_owned())], &mut vm).unwrap();
+    let outer =
+        Value::from_runtime_array(vec![inner.clone(), inner, Value::from(1.0)], &mut vm).unwrap();
+    // Same array can be nested more than once, it's not a cycle
+    assert_eq!(outer.to_string(), "[[a], [a], 1]");
+    assert_eq!(outer.debug_repr(), r#"[["a"], ["a"], 1]"#);
+    vm.reset_vm();
+}
+
+#[test]
+fn array_containing_itself_display() {
+    use crate::value::objects::ObjectType;
+
+    let mut vm = VM::new();
+    let array = Value::from_runtime_array(vec![Value::from(1.0)], &mut vm).unwrap();
+    let Value::Obj(pointer) = array else {
+        unreachable!()
+    };
+    // Arrays can't be changed from Lox yet, but the host can make an array contain itself
+    unsafe {
+        if let ObjectType::Array(values) = &mut (*pointer.as_ptr()).ty {
+            values.push(array.clone());
+        }
+    }
+    let nested = Value::from_runtime_array(vec![array.clone()], &mut vm).unwrap();
+
+    assert_eq!(array.to_string(), "[1, [...]]");
+    assert_eq!(array.debug_repr(), "[1, [...]]");
+    assert_eq!(nested.to_string(), "[[1, [...]]]");
+    vm.reset_vm();
+}
+
+#[test]
+fn loose_equality_coerces_numeric_strings() {
+    let mut vm = VM::new();
+    let one = Value::from(1.0);
+    let runtime = Value::from_runtime_str(" 1.0 ".to_owned(), &mut vm).unwrap();
+
+    for string in ["1", "1e0", "+1"] {
+        let string = Value::from(string.to_owned());
+        assert!(one.loose_equals(&string) && string.loose_equals(&one));
+        // Strict equality never compares different types
+        assert!(!one.values_equal(&string));
+    }
+    assert!(one.loose_equals(&runtime));
+
+    for string in ["one", "", "1a", "inf"] {
+        assert!(
+            !one.loose_equals(&Value::from(string.to_owned())),
+            "{string}"
+        );
+    }
+    assert!(!Value::from(f64::INFINITY).loose_equals(&Value::from("inf".to_owned())));
+    assert!(!one.loose_equals(&Value::from(true)));
+    assert!(!Value::new_nil().loose_equals(&Value::from(0.0)));
+    assert!(!Value::from("1".to_owned()).loose_equals(&Value::from("1.0".to_owned())));
+    assert!(runtime.loose_equals(&Value::from(" 1.0 ".to_owned())));
+
+    vm.reset_vm();
+}
+
+#[test]
+fn number_formats() {
+    use crate::value::NumberFormat;
+
+    let cases = [
+        (NumberFormat::Default, 1234.5, "1234.5"),
+        (NumberFormat::Default, 2.0, "2"),
+        (NumberFormat::Fixed(2), 1234.5, "1234.50"),
+        (NumberFormat::Fixed(2), 0.125, "0.12"),
+        (NumberFormat::Fixed(0), 2.5, "2"),
+        (NumberFormat::Fixed(2), -0.001, "-0.00"),
+        (NumberFormat::Scientific, 1234.5, "1.2345e3"),
+        (NumberFormat::Scientific, 0.01, "1e-2"),
+    ];
+    for (format, number, expected) in cases {
+        assert_eq!(format.format(number), expected, "{format:?} {number}");
+    }
+    // Special values aren't affected by the format
+    for format in [NumberFormat::Fixed(2), NumberFormat::Scientific] {
+        assert_eq!(format.format(f64::NAN), "NaN");
+        assert_eq!(format.format(f64::INFINITY), "inf");
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    value::Value,
+    vm::{VM, VMError},
+};
+
+impl VM {
+    /// Pushes a new array with elements of `left` followed by elements of `right`. Both operands are left unchanged.
+    pub(super) fn concatenate_arrays(
+        &mut self,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(), VMError> {
+        let (left, right) = (left.as_array_ref(), right.as_array_ref());
+        let mut values = Vec::with_capacity(left.len() + right.len());
+        values.extend_from_slice(left);
+        values.extend_from_slice(right);
+        // Array is created at runtime, so it should be tracked for garbage collection
+        let array = Value::from_runtime_array(values, self)?;
+        self.push(array);
+        Ok(())
+    }
+
+    /// Moves the values from the top of the stack into a new array, and pushes that array onto the stack
+    pub(super) fn op_build_array(&mut self) -> Result<(), VMError> {
+        let count = self.read_byte()?;
+        // Values are pushed in order, so first value is deepest in the stack
+        let start = self
+            .stack
+            .len()
+            .checked_sub(count as usize)
+            .ok_or_else(|| {
+                self.construct_runtime_error(format_args!("Expected {count} values on the stack"))
+            })?;
+        let values = self.stack.split_off(start);
+        // Array is created at runtime, so it should be tracked for garbage collection
+        let array = Value::from_runtime_array(values, self)?;
+        self.push(array);
+        Ok(())
+    }
+
+    /// Pops the index and the array, and pushes the element at that index. Negative index is adjusted by adding the length
+    /// of the array, so it counts from the end. Index still out of range after that is a runtime error.
+    pub(super) fn op_get_index(&mut self) -> Result<(), VMError> {
+        let (Some(index), Some(array)) = (self.pop(), self.pop()) else {
+            return Err(self.construct_runtime_error(format_args!("Expected value on the stack")));
+        };
+
+        if !array.is_array() {
+            return Err(self.construct_runtime_error(format_args!(
+                "Only arrays can be indexed, '{}' can't.",
+                array.type_name()
+            )));
+        }
+        let number = if index.is_number() {
+            index.clone().to_number()
+        } else {
+            f64::NAN
+        };
+        if number.fract() != 0.0 {
+            return Err(self.construct_runtime_error(format_args!(
+                "Array index should be a whole number but got '{}'.",
+                index.debug_repr()
+            )));
+        }
+
+        let values = array.as_array_ref();
+        #[allow(clippy::cast_precision_loss)]
+        let length = values.len() as f64;
+        let position = if number < 0.0 {
+            number + length
+        } else {
+            number
+        };
+        if !(0.0..length).contains(&position) {
+            return Err(self.construct_runtime_error(format_args!(
+                "Index {index} is out of range for array of length {length}."
+            )));
+        }
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let element = values[position as usize].clone();
+        self.push(element);
+        Ok(())
+    }
+
+    /// Pops the array and pushes its elements onto the stack, in order. Used to destructure multiple values.
+    pub(super) fn op_unpack(&mut self) -> Result<(), VMError> {
+        let count = self.read_byte()?;
+        let array = self.pop().ok_or_else(|| {
+            self.construct_runtime_error(format_args!("Expected value on the stack"))
+        })?;
+
+        if !array.is_array() {
+            return Err(self.construct_runtime_error(format_args!("Can only unpack an array.")));
+        }
+
+        let values = array.as_array_ref();
+        if values.len() != count as usize {
+            let length = values.len();
+            return Err(self.construct_runtime_error(format_args!(
+                "Expected {count} values to unpack but got {length}."
+            )));
+        }
+
+        // Elements are aliased, not copied. They're still owned by the `objects` list.
+        self.stack.extend_from_slice(values);
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use crate::value::Value;
+use crate::{
+    value::Value,
+    vm::{VM, errors::VMError},
+};
 
 pub struct CallFrame {
     pub(super) function: Value,
@@ -15,38 +18,110 @@ impl CallFrame {
         }
     }
 
-    pub(super) fn read_byte(&mut self) -> u8 {
+    /// Returns `None` if instruction pointer is at the end of the bytecode
+    pub(super) fn read_byte(&mut self) -> Option<u8> {
         // First byte should be the instruction byte of the code
-        let instruction_byte = self.function.as_function_ref().chunk.code[self.ip_offset];
+        let instruction_byte = *self
+            .function
+            .as_function_ref()
+            .chunk
+            .code
+            .get(self.ip_offset)?;
         // Increment instruction pointer after reading the byte
         self.ip_offset += 1;
 
-        instruction_byte
+        Some(instruction_byte)
     }
 
-    pub(super) fn read_u16(&mut self) -> u16 {
+    /// Returns `None` if there aren't two bytes left in the bytecode
+    pub(super) fn read_u16(&mut self) -> Option<u16> {
         // Read bytes
-        let bytes = &self.function.as_function_ref().chunk.code[self.ip_offset..self.ip_offset + 2];
+        let bytes = self
+            .function
+            .as_function_ref()
+            .chunk
+            .code
+            .get(self.ip_offset..self.ip_offset + 2)?;
+        let value = u16::from_be_bytes([bytes[0], bytes[1]]);
         // Advance two bytes
         self.ip_offset += 2;
-        // Convert to u16
-        u16::from_be_bytes([bytes[0], bytes[1]])
+        Some(value)
     }
 
-    /// Reads constant from constant pool
-    pub(super) fn read_constant(&mut self) -> Value {
+    /// Reads constant from constant pool. Returns index of the constant as error if it's not in the pool.
+    /// Returns `None` if the bytecode ends before the index.
+    pub(super) fn read_constant(&mut self) -> Option<Result<Value, u8>> {
         // We don't directly store constants on bytecode. Bytecode has the
         // index/offset of constant. We get that index from bytecode.
-        let constant_position = self.function.as_function_ref().chunk.code[self.ip_offset];
+        let constant_position = self.read_byte()?;
         // Gets the value from constant pool.
         // This is not to be used in production. `constant_position` implies that there
         // would be maximum 256 constants, which should not be the case.
         // Multi-byte operations needed to be introduced to handle that
-        let constant: Value =
-            self.function.as_function_ref().chunk.constants[constant_position as usize].clone();
-        // increment instruction pointer by 1, because we've consumed 1 byte
-        self.ip_offset += 1;
-        // return the value
-        constant
+        let constant = self
+            .function
+            .as_function_ref()
+            .chunk
+            .constants
+            .get(constant_position as usize)
+            .cloned()
+            .ok_or(constant_position);
+        Some(constant)
+    }
+}
+
+/// Reads bytecode of the current frame, and turns malformed bytecode into runtime errors, instead of panics
+impl VM {
+    /// Reads next byte of the current frame. Bytecode of a function should end with a return instruction, so running out of bytes is an error.
+    pub(super) fn read_byte(&mut self) -> Result<u8, VMError> {
+        match self.current_frame()?.read_byte() {
+            Some(byte) => Ok(byte),
+            None => Err(self.end_of_bytecode_error()),
+        }
+    }
+
+    /// Reads two byte operand of a jump instruction
+    pub(super) fn read_u16(&mut self) -> Result<u16, VMError> {
+        match self.current_frame()?.read_u16() {
+            Some(value) => Ok(value),
+            None => Err(self.end_of_bytecode_error()),
+        }
+    }
+
+    /// Reads a constant index, and returns the constant at that index
+    pub(super) fn read_constant(&mut self) -> Result<Value, VMError> {
+        match self.current_frame()?.read_constant() {
+            Some(Ok(constant)) => Ok(constant),
+            Some(Err(index)) => {
+                let size = self
+                    .current_frame()?
+                    .function
+                    .as_function_ref()
+                    .chunk
+                    .constants
+                    .len();
+                Err(self.construct_runtime_error(format_args!(
+                    "Constant index {index} is out of bounds of constant pool of size {size}."
+                )))
+            }
+            None => Err(self.end_of_bytecode_error()),
+        }
+    }
+
+    /// Reads a constant which has to be a string, like name of a variable or a property
+    pub(super) fn read_string_constant(&mut self) -> Result<String, VMError> {
+        let constant = self.read_constant()?;
+        if !constant.is_string() {
+            return Err(self.construct_runtime_error(format_args!(
+                "Expected a string constant but got '{}'.",
+                constant.type_name()
+            )));
+        }
+        Ok(constant.as_string())
+    }
+
+    #[cold]
+    fn end_of_bytecode_error(&mut self) -> VMError {
+        self.construct_runtime_error(format_args!("Reached end of bytecode without return."))
     }
 }
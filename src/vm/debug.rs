@@ -2,7 +2,7 @@ use crate::vm::VM;
 
 impl VM {
     #[cfg(feature = "debug_trace_execution")]
-    pub(super) fn debug(&mut self) {
+    pub(super) fn debug(&mut self) -> Result<(), crate::vm::VMError> {
         use crate::debug::Debug;
         print!("          ");
         for value in &self.stack {
@@ -11,11 +11,12 @@ impl VM {
             print!(" ]");
         }
         println!("");
-        let offset = self.current_frame().ip_offset;
+        let offset = self.current_frame()?.ip_offset;
         Debug::dissassemble_instruction(
-            &self.current_frame().function.as_function_ref().chunk,
+            &self.current_frame()?.function.as_function_ref().chunk,
             offset,
         );
+        Ok(())
     }
 
     /// Show items in garbadge collection
@@ -1,13 +1,17 @@
 use std::fmt::Arguments;
 use std::fmt::Write as _;
 
-use crate::{compiler::errors::CompilerError, vm::VM};
+use crate::{compiler::errors::CompilerError, constants::ExitCode, vm::VM};
 
 #[derive(Debug)]
 /// Errors related to virtual machine
 pub enum VMError {
     CompileError(CompilerError),
     RuntimeError(String),
+    /// Script called `exit(code)`. It's returned like an error, so execution stops and the VM unwinds, but it's up to the host to exit the process.
+    Exit(u8),
+    /// Source code couldn't be read, e.g. file passed to `run_file` doesn't exist
+    IoError(String),
 }
 
 /// This trait implementation makes it easier to customize error output, to look nicer.
@@ -20,6 +24,25 @@ impl std::fmt::Display for VMError {
             Self::RuntimeError(e) => {
                 write!(f, "{e}")
             }
+            Self::Exit(code) => {
+                write!(f, "Exited with code {code}")
+            }
+            Self::IoError(e) => {
+                write!(f, "{e}")
+            }
+        }
+    }
+}
+
+impl VMError {
+    /// Returns the code the process should exit with for this error. Code passed to `exit()` is used as it is.
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::CompileError(_) => ExitCode::CompileError.into(),
+            Self::RuntimeError(_) => ExitCode::RuntimeError.into(),
+            Self::IoError(_) => ExitCode::IoError.into(),
+            Self::Exit(code) => (*code).into(),
         }
     }
 }
@@ -29,21 +52,23 @@ impl VM {
     /// It gets dynamic arguments, and constructs proper error
     pub(crate) fn construct_runtime_error(&mut self, arguments: Arguments) -> VMError {
         let mut message = format!("{arguments}\n");
-        for frame in self.frames.iter().rev() {
+        for (index, frame) in self.frames.iter().enumerate().rev() {
             let function = &frame.function.as_function_ref();
-            let instruction = frame.ip_offset - 1;
-            let _ = write!(message, "[line {}] in ", function.chunk.lines[instruction]);
+            // Nothing has been read from an empty chunk, so there's no line to point at
+            let instruction = frame.ip_offset.saturating_sub(1);
+            let line = function.chunk.lines.get(instruction).copied().unwrap_or(0);
+            let _ = write!(message, "[line {line}] in ");
 
             if let Some(name) = function.name.as_ref() {
                 let _ = writeln!(message, "{name}()");
-            } else {
+            } else if index == 0 {
+                // Bottom frame is always the top-level script
                 message.push_str("<script>\n");
+            } else {
+                message.push_str("<anonymous fn>\n");
             }
         }
 
-        // Error occured, reset stack.
-        self.reset_vm();
-
         // Return proper error
         VMError::RuntimeError(message)
     }
@@ -1,5 +1,3 @@
-use std::cmp::Ordering;
-
 use crate::{
     constants::FRAMES_MAX,
     value::Value,
@@ -7,53 +5,92 @@ use crate::{
 };
 
 impl VM {
-    pub(super) fn op_return(&mut self) -> bool {
-        let result = self.pop().unwrap();
-        match self.frames.len().cmp(&1) {
-            Ordering::Equal => {
-                self.pop();
-                // End of bytecode
-                return true;
-            }
-            Ordering::Greater => {
-                self.pop();
-                self.pop();
-            }
-            Ordering::Less => {}
+    /// Pops the result and the frame of returning function. Returns the result if it was the top-level script, which means bytecode has ended.
+    pub(super) fn op_return(&mut self, returns_nil: bool) -> Result<Option<Value>, VMError> {
+        // Implicit returns don't push `nil` on the stack first
+        let result = if returns_nil {
+            Value::new_nil()
+        } else {
+            self.pop().ok_or_else(|| {
+                self.construct_runtime_error(format_args!("Expected return value on the stack"))
+            })?
+        };
+        if self.frames.is_empty() {
+            self.push(result);
+            return Ok(None);
         }
+        // Discard the arguments and locals of the function, and the callee itself, which is right below the arguments
+        let callee_index = self.current_frame()?.starting_offset.saturating_sub(1);
+        self.stack.truncate(callee_index);
+        self.frames.pop();
 
+        if self.frames.is_empty() {
+            // End of bytecode. Last frame is popped too, so the VM is left clean for the next program.
+            return Ok(Some(result));
+        }
         self.push(result);
-        self.frames.pop();
         // It's just end of a called function, not end of bytecode.
-        false
+        Ok(None)
     }
 
     pub(super) fn op_call(&mut self) -> Result<(), VMError> {
-        let arg_count = self.current_frame().read_byte();
-        let callee_index = self.stack.len() - (arg_count as usize) - 1;
+        let arg_count = self.read_byte()?;
+        let callee_index = self
+            .stack
+            .len()
+            .checked_sub(arg_count as usize + 1)
+            .ok_or_else(|| {
+                self.construct_runtime_error(format_args!("Expected callee on the stack"))
+            })?;
         let callee = self.stack[callee_index].clone();
         self.call_value(callee, arg_count)
     }
 
+    /// Checks the type of an annotated parameter, when the function starts running. Natives can be passed where a function is expected.
+    pub(super) fn op_check_type(&mut self) -> Result<(), VMError> {
+        let slot = self.read_byte()?;
+        let expected = self.read_string_constant()?;
+        let index = self.current_frame()?.starting_offset + slot as usize;
+        let Some(value) = self.stack.get(index) else {
+            return Err(
+                self.construct_runtime_error(format_args!("Invalid local variable slot {slot}"))
+            );
+        };
+
+        let actual = value.type_name();
+        if actual == expected || (expected == "function" && value.is_native()) {
+            return Ok(());
+        }
+        let position = u16::from(slot) + 1;
+        Err(self.construct_runtime_error(format_args!(
+            "Expected argument {position} to be '{expected}' but got '{actual}'."
+        )))
+    }
+
     fn call_value(&mut self, callee: Value, arg_count: u8) -> Result<(), VMError> {
         if callee.is_function() {
             return self.call(callee, arg_count);
         } else if callee.is_native() {
-            let native = callee.as_native_ref();
+            let native = *callee.as_native_ref();
 
-            let mut values = vec![];
-            for _ in 0..arg_count {
-                values.push(self.pop().unwrap());
-            }
+            // Arguments are on top of the stack, first argument first
+            let values = self.stack.split_off(self.stack.len() - arg_count as usize);
+            // Pop the callee
             self.pop();
 
-            let result = native(arg_count, values);
+            let result = native(self, values)?;
             self.push(result);
 
             return Ok(());
         }
 
-        Err(self.construct_runtime_error(format_args!("Can only call functions and classes")))
+        // Discard the callee and its arguments, so the stack is consistent while error is constructed
+        let callee_index = self.stack.len() - arg_count as usize - 1;
+        self.stack.truncate(callee_index);
+        Err(self.construct_runtime_error(format_args!(
+            "Can only call functions, '{}' is not callable.",
+            callee.type_name()
+        )))
     }
 
     ///
@@ -64,8 +101,14 @@ impl VM {
         let arity = function.as_function_ref().arity;
 
         if arg_count != arity {
+            // Name of the function helps to find the faulty call when there are many calls in a script
+            let name = function
+                .as_function_ref()
+                .name
+                .as_ref()
+                .map_or_else(|| "<anonymous fn>".to_owned(), |name| format!("'{name}'"));
             let error = self.construct_runtime_error(format_args!(
-                "Expected {arity} arguments but got {arg_count}."
+                "Expected {arity} arguments but got {arg_count} when calling {name}."
             ));
             return Err(error);
         }
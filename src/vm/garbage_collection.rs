@@ -1,4 +1,7 @@
-use std::{collections::HashSet, ptr::NonNull};
+use std::{
+    collections::HashSet,
+    ptr::{self, NonNull},
+};
 
 use crate::{
     value::{
@@ -12,21 +15,52 @@ impl VM {
     pub fn reset_vm(&mut self) {
         #[cfg(feature = "debug_trace_execution")]
         self.display_garbage_items();
+        // Reset stack to its initial state. Should be done before freeing the list, because stack needs to know which objects are owned by the list.
+        self.reset_stack();
+        self.captures.clear();
+        // Functions are freed below, cached slots of their instructions are useless now
+        self.global_cache.clear();
         // Remove items from garbage collection
         self.free_objects();
-        // Reset stack to its initial state
-        self.reset_stack();
     }
 
-    /// Empties the stack and resets the top to '0'
+    /// Empties the stack and resets the top to '0'. Objects tracked by `objects` list are skipped, because `free_objects` frees them. Freeing them here would free them twice.
     pub fn reset_stack(&mut self) {
-        let mut hash_set = HashSet::new();
+        // Pointers in this set are never freed by the stack
+        let mut hash_set = self.tracked_objects();
         while let Some(value) = self.pop() {
             self.free_stack_object_memory(value, &mut hash_set);
         }
         self.frames = vec![];
     }
 
+    /// Frees a top-level function created outside of the VM, like a compiled script converted into a `Value`, once it won't
+    /// be run anymore. Its constants are freed with it, but functions declared in it aren't, globals may still reference them.
+    /// Function must not be used after this.
+    pub fn free_script(&mut self, script: Value) {
+        // Script isn't tracked by `objects`, unless caller created it with the VM, which frees it in `reset_vm` then
+        let address = ptr::from_ref(script.as_function_ref()) as usize;
+        // Slots cached for instructions of the script won't be read again, and its address can be reused by another function
+        self.global_cache
+            .retain(|&(function, _), _| function != address);
+        let mut tracked = self.tracked_objects();
+        self.free_stack_object_memory(script, &mut tracked);
+    }
+
+    /// Collects pointers of all objects in the `objects` list
+    pub(crate) fn tracked_objects(&self) -> HashSet<ObjectPointer> {
+        let mut pointers = HashSet::new();
+        let mut head = self.objects;
+        while let Some(obj) = head {
+            pointers.insert(obj);
+            // Unsafe is required to dereference the raw pointer
+            unsafe {
+                head = (*obj.as_ptr()).next;
+            }
+        }
+        pointers
+    }
+
     /// Responsible for freeing the memory allocated by runtime objects, such as string
     pub fn free_objects(&mut self) {
         // Iterate over the list of objects
@@ -43,7 +77,7 @@ impl VM {
         }
     }
 
-    /// Frees object memory behind raw pointers, such as a string or a function
+    /// Frees object memory behind raw pointers, such as a string or a function. Pointers already in `hash_set` are skipped, and freed pointers are added to it.
     pub fn free_stack_object_memory(
         &mut self,
         value: Value,
@@ -1,6 +1,7 @@
 /// This module handles all the stuff a VM is supposed to do
 /// It takes source code, compiles it, gets bytecode (stored in chunk) from compiler
 /// and then execute that bytecode
+mod arrays;
 mod call_frame;
 mod debug;
 pub mod errors;
@@ -8,18 +9,30 @@ mod functions;
 mod garbage_collection;
 mod native;
 mod operations;
+mod properties;
+#[cfg(test)]
+mod tests;
 mod variables;
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
 
 use crate::{
-    chunk::OpCode,
+    chunk::{Chunk, OpCode},
     constants::FRAMES_MAX,
-    value::{Value, objects::ObjectNode},
+    value::{
+        NumberFormat, Value,
+        objects::{FunctionObject, ObjectNode},
+    },
     vm::{
         call_frame::CallFrame,
         errors::VMError,
-        native::{clock_native, println},
+        native::{
+            assert_eq, byte_len, capture_begin, capture_end, clock_native, contains, eprint, exit,
+            format, join, len, max, min, println, split, str,
+        },
     },
 };
 
@@ -29,9 +42,33 @@ pub struct VM {
     pub stack: Vec<Value>,
     /// A linked list to track Objects stored on heap, mainly used for garbage collection. Linked list is not the best data structure used for garbage collection. Just keeping it simple for now.
     pub objects: ObjectNode,
-    /// A Datastructure, also known as `HashTable`, to store global variables for faster insertion and lookup.
-    globals: HashMap<String, Value>,
+    /// A Datastructure, also known as `HashTable`, to find slot of a global variable in `global_values` by its name.
+    globals: HashMap<String, usize>,
+    /// Values of global variables. Globals are never removed, so a slot stays valid for the life of the VM, and can be cached.
+    global_values: Vec<Value>,
+    /// Names of global variables, by their slot
+    global_names: Vec<String>,
+    /// Slots of globals read by `OpGetGlobal` instructions, by address of the function and offset of the instruction in it.
+    /// Same function can be run by many VMs, and slots of a VM mean nothing to others, so the cache is kept here and not in bytecode.
+    /// Entries of a script are evicted when it's freed, so a long REPL session doesn't keep them for every line.
+    global_cache: HashMap<(usize, usize), usize>,
     pub frames: Vec<CallFrame>,
+    /// Disallows redefining an existing global variable or function. Useful in REPL to catch typos.
+    pub strict_globals: bool,
+    /// Floors result of `/` when both operands are whole numbers, so `7 / 2` is `3`. Division is floating-point by default.
+    pub integer_division: bool,
+    /// Makes `==` and `!=` treat a number and a string containing the same number as equal, so `1 == "1"` is true. Off by default.
+    pub loose_equality: bool,
+    /// How numbers are written by `print`, and natives writing values as text, like `println()` and `str()`. Numbers added
+    /// to strings with `+` are always written in the default format.
+    pub number_format: NumberFormat,
+    /// Where `print` statement writes values. Standard output by default.
+    output: Box<dyn Write>,
+    /// Buffers collecting output between `capture_begin()` and `capture_end()`, innermost capture last. While a capture is
+    /// active, printed values are appended to the innermost buffer instead of being written to `output`.
+    captures: Vec<String>,
+    /// Where diagnostics, like output of `eprint()`, are written. Standard error by default.
+    error_output: Box<dyn Write>,
 }
 
 impl Default for VM {
@@ -41,9 +78,16 @@ impl Default for VM {
 }
 
 impl VM {
-    /// Returns a new instance of the VM
+    /// Returns a new instance of the VM. Call frames aren't allocated until a function is called, and grow as calls get deeper.
     #[must_use]
     pub fn new() -> Self {
+        Self::with_frames_capacity(0)
+    }
+
+    /// Returns a new instance of the VM, with memory reserved for `capacity` call frames upfront.
+    /// Capacity is clamped to `FRAMES_MAX`, because calls can't go deeper than that.
+    #[must_use]
+    pub fn with_frames_capacity(capacity: usize) -> Self {
         Self {
             // All values should be nil/empty by default
             stack: Vec::new(),
@@ -51,20 +95,80 @@ impl VM {
             objects: None,
             // No global variables when vm is initialized.
             globals: HashMap::new(),
-            frames: Vec::with_capacity(FRAMES_MAX),
+            global_values: Vec::new(),
+            global_names: Vec::new(),
+            global_cache: HashMap::new(),
+            frames: Vec::with_capacity(capacity.min(FRAMES_MAX)),
+            strict_globals: false,
+            integer_division: false,
+            loose_equality: false,
+            number_format: NumberFormat::default(),
+            output: Box::new(io::stdout()),
+            captures: Vec::new(),
+            error_output: Box::new(io::stderr()),
         }
     }
 
-    /// Compiles source code, gets bytecode from compiler, and executes that bytecode
+    /// Compiles source code, gets bytecode from compiler, and executes that bytecode. Returns the value returned by the top-level script.
     /// # Errors
     ///
     /// Returns `VMError` if there's any runtime error
-    pub fn interpret(&mut self) -> Result<(), VMError> {
+    pub fn interpret(&mut self) -> Result<Value, VMError> {
         self.define_native("clock", clock_native)?;
         self.define_native("println", println)?;
+        self.define_native("eprint", eprint)?;
+        self.define_native("len", len)?;
+        self.define_native("byte_len", byte_len)?;
+        self.define_native("exit", exit)?;
+        self.define_native("format", format)?;
+        self.define_native("min", min)?;
+        self.define_native("max", max)?;
+        self.define_native("assert_eq", assert_eq)?;
+        self.define_native("contains", contains)?;
+        self.define_native("split", split)?;
+        self.define_native("join", join)?;
+        self.define_native("str", str)?;
+        self.define_native("capture_begin", capture_begin)?;
+        self.define_native("capture_end", capture_end)?;
         self.run()
     }
 
+    /// Calls a top-level function, like a compiled script, and runs it until it returns. Returns the value returned by the function.
+    ///
+    /// Globals, and runtime objects they may reference, persist across calls. Stack and call frames are reset, whether the function
+    /// succeeds or fails, so the next function can be run without calling `reset_vm`. Runtime objects are only freed by `reset_vm`.
+    /// Function itself is owned by the caller, see `free_script`.
+    /// # Errors
+    ///
+    /// Returns `VMError` if there's any runtime error
+    pub fn run_isolated(&mut self, function: Value) -> Result<Value, VMError> {
+        // Function occupies the first slot of its frame, like a called function does
+        self.push(function.clone());
+        let result = self.call(function, 0).and_then(|()| self.interpret());
+        // Values left on the stack are either runtime objects, owned by `objects` list, or the function, owned by the caller
+        self.stack.clear();
+        self.frames.clear();
+        if result.is_err() {
+            // Script can't end captures it has begun anymore, output of next scripts shouldn't go into them
+            self.captures.clear();
+        }
+        result
+    }
+
+    /// Runs hand written bytecode, without the compiler. Chunk is wrapped in a nameless function and run like a top-level script,
+    /// so it should end with a return instruction. Returns the value returned by the chunk.
+    /// # Errors
+    ///
+    /// Returns `VMError` if there's any runtime error, including invalid bytecode
+    pub fn run_chunk(&mut self, chunk: Chunk) -> Result<Value, VMError> {
+        let mut function = FunctionObject::new();
+        function.chunk = chunk;
+        let function = Value::from(function);
+        let result = self.run_isolated(function.clone());
+        self.free_script(function);
+        result
+    }
+
     pub fn replace_or_push(&mut self, value: Value, index: usize) {
         if self.stack.len() <= index {
             self.push(value);
@@ -82,30 +186,100 @@ impl VM {
         self.stack.pop()
     }
 
-    pub fn current_frame(&mut self) -> &mut CallFrame {
+    /// Writes printed text to the innermost capture, if output is being captured, otherwise to `output`. Output is flushed,
+    /// so it isn't delayed until a new line is printed, and appears in correct order with errors written to stderr.
+    pub(super) fn write_output(&mut self, text: &str) -> io::Result<()> {
+        if let Some(capture) = self.captures.last_mut() {
+            capture.push_str(text);
+            return Ok(());
+        }
+        self.output.write_all(text.as_bytes())?;
+        self.output.flush()
+    }
+
+    /// Redirects output of `print` statements, e.g. to a buffer to capture it
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Redirects output of `eprint()`, e.g. to a buffer to capture it
+    pub fn set_error_output(&mut self, output: impl Write + 'static) {
+        self.error_output = Box::new(output);
+    }
+
+    /// Defines a global variable, or overwrites it if it already exists. Can be used to inject values into a script before running it.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.define_global(name, value);
+    }
+
+    /// Returns the value of a global variable, or `None` if it isn't defined.
+    ///
+    /// Object values, e.g. strings created at runtime, are owned by the `VM` and shouldn't be used after `reset_vm`.
+    #[must_use]
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.global(name).cloned()
+    }
+
+    /// Returns the frame of the function being executed.
+    /// # Errors
+    ///
+    /// Returns `VMError` if there's no active frame, e.g. when `run` is called before calling any function
+    pub fn current_frame(&mut self) -> Result<&mut CallFrame, VMError> {
+        if self.frames.is_empty() {
+            return Err(self.construct_runtime_error(format_args!("No active call frame.")));
+        }
         let top_index = self.frames.len() - 1;
-        &mut self.frames[top_index]
+        Ok(&mut self.frames[top_index])
+    }
+
+    /// Moves instruction pointer of the current frame `offset` bytes forward. Compiler never emits a jump past the end of the bytecode,
+    /// but hand written or corrupt bytecode could, so target is checked instead of trusted.
+    fn jump_forward(&mut self, offset: u16) -> Result<(), VMError> {
+        let frame = self.current_frame()?;
+        let code_len = frame.function.as_function_ref().chunk.code.len();
+        let target = frame.ip_offset + usize::from(offset);
+        if target > code_len {
+            return Err(self.construct_runtime_error(format_args!(
+                "Jump target {target} is past the end of bytecode of length {code_len}."
+            )));
+        }
+        self.current_frame()?.ip_offset = target;
+        Ok(())
     }
 
+    /// Moves instruction pointer of the current frame `offset` bytes back, for loops. Errors instead of underflowing if offset is larger than the position.
+    fn jump_back(&mut self, offset: u16) -> Result<(), VMError> {
+        let frame = self.current_frame()?;
+        let Some(target) = frame.ip_offset.checked_sub(usize::from(offset)) else {
+            let position = frame.ip_offset;
+            return Err(self.construct_runtime_error(format_args!(
+                "Loop offset {offset} jumps before start of bytecode from position {position}."
+            )));
+        };
+        frame.ip_offset = target;
+        Ok(())
+    }
+
+    /// Executes bytecode of the current frame, until top-level script returns. Returns the value returned by the script.
     /// # Errors
     ///
     /// Returns `VMError` if there's any runtime error
-    pub fn run(&mut self) -> Result<(), VMError> {
+    pub fn run(&mut self) -> Result<Value, VMError> {
         loop {
             #[cfg(feature = "debug_trace_execution")]
-            self.debug();
+            self.debug()?;
 
-            let instruction_byte = self.current_frame().read_byte();
+            let instruction_byte = self.read_byte()?;
             // Try to convert that byte to `OpCode` enum
             if let Ok(opcode) = OpCode::try_from(instruction_byte) {
                 // Conversion successful. Match opcode with different arms
                 // to execute instruction
                 match opcode {
                     // It means this is final instruction in the byte code
-                    OpCode::OpReturn => {
-                        // If it's end of bytecode, just return.
-                        if self.op_return() {
-                            return Ok(());
+                    OpCode::OpReturn | OpCode::OpReturnNil => {
+                        // If it's end of bytecode, just return the result.
+                        if let Some(result) = self.op_return(opcode == OpCode::OpReturnNil)? {
+                            return Ok(result);
                         }
                     }
                     // Usually used for expression statements. These statements may produce a result but this result will be popped because expression statements are only used for side effects.
@@ -114,21 +288,53 @@ impl VM {
                             // Return error if value on stack is not found
                             self.construct_runtime_error(format_args!("Expected value on the stack")))?;
                     }
+                    OpCode::OpPopN => {
+                        let count = self.read_byte()? as usize;
+                        let length = self.stack.len().checked_sub(count).ok_or_else(|| {
+                            self.construct_runtime_error(format_args!(
+                                "Expected {count} values on the stack"
+                            ))
+                        })?;
+                        self.stack.truncate(length);
+                    }
+                    OpCode::OpDup => {
+                        // Copy aliases the same object, if value is an object
+                        let value = self.stack.last().cloned().ok_or_else(||
+                            // Return error if value on stack is not found
+                            self.construct_runtime_error(format_args!("Expected value on the stack")))?;
+                        self.push(value);
+                    }
+                    OpCode::OpTuck => {
+                        let index = self.stack.len().checked_sub(2).ok_or_else(|| {
+                            self.construct_runtime_error(format_args!(
+                                "Expected two values on the stack"
+                            ))
+                        })?;
+                        // Copy aliases the same object, if value is an object
+                        let value = self.stack[index + 1].clone();
+                        self.stack.insert(index, value);
+                    }
                     OpCode::OpPrint => {
                         let v = self.pop().ok_or_else(||
                             // Return error if value on stack is not found
                             self.construct_runtime_error(format_args!("Expected value on the stack")))?;
-                        print!("{v}");
+                        // `print` doesn't append a new line
+                        let text = v.format_with(self.number_format);
+                        self.write_output(&text).map_err(|e| {
+                            self.construct_runtime_error(format_args!("Can't print value: {e}"))
+                        })?;
                     }
-                    OpCode::OpGetLocal => self.op_get_local(),
-                    OpCode::OpSetLocal => self.op_set_local(),
+                    OpCode::OpGetLocal => self.op_get_local()?,
+                    OpCode::OpSetLocal => self.op_set_local()?,
                     OpCode::OpDefineGlobal => self.op_define_global()?,
                     OpCode::OpGetGlobal => self.op_get_global()?,
                     OpCode::OpSetGlobal => self.op_set_global()?,
+                    OpCode::OpGetProperty => self.op_get_property()?,
+                    OpCode::OpGetIndex => self.op_get_index()?,
                     // Read constant from the constant pool
                     OpCode::OpConstant => {
                         // Get constant value from constant pool
-                        let constant = self.current_frame().read_constant();
+                        let constant = self.read_constant()?;
                         // Push that constant onto the stack
                         self.push(constant);
                     }
@@ -144,7 +350,9 @@ impl VM {
                     | OpCode::OpMultiply
                     | OpCode::OpDivide
                     | OpCode::OpGreater
-                    | OpCode::OpLess => self.binary_op(&opcode)?,
+                    | OpCode::OpLess
+                    | OpCode::OpGreaterEqual
+                    | OpCode::OpLessEqual => self.binary_op(&opcode)?,
 
                     // Push `Nil` onto the stack
                     OpCode::OpNil => {
@@ -167,26 +375,62 @@ impl VM {
                     OpCode::OpEqual => self.op_equal()?,
                     OpCode::OpJumpIfFalse => {
                         // Reads the two bytes of distance being jumped
-                        let offset = self.current_frame().read_u16();
-                        // Result of the condition
-                        let if_condition = &self.stack[self.stack.len() - 1];
+                        let offset = self.read_u16()?;
+                        // Result of the condition, it's kept on the stack
+                        let Some(if_condition) = self.stack.last() else {
+                            return Err(self.construct_runtime_error(format_args!(
+                                "Expected value on the stack"
+                            )));
+                        };
                         // If condition is false, then perform the jump, other wise continue executing the statements
                         if if_condition.clone().is_falsey() {
-                            self.current_frame().ip_offset += offset as usize;
+                            self.jump_forward(offset)?;
+                        }
+                    }
+                    OpCode::OpPopJumpIfFalse => {
+                        let offset = self.read_u16()?;
+                        // Condition isn't needed after the jump, so it's popped here instead of with a separate `OpPop`
+                        let condition = self.pop().ok_or_else(|| {
+                            self.construct_runtime_error(format_args!(
+                                "Expected value on the stack"
+                            ))
+                        })?;
+                        if condition.is_falsey() {
+                            self.jump_forward(offset)?;
+                        }
+                    }
+                    OpCode::OpJumpIfNotNil => {
+                        let offset = self.read_u16()?;
+                        // Value is the result of `??` if it's not nil, so it stays on the stack
+                        let Some(value) = self.stack.last() else {
+                            return Err(self.construct_runtime_error(format_args!(
+                                "Expected value on the stack"
+                            )));
+                        };
+                        if !value.is_nil() {
+                            self.jump_forward(offset)?;
                         }
                     }
                     OpCode::OpJump => {
                         // Read distance to jump
-                        let offset = self.current_frame().read_u16();
+                        let offset = self.read_u16()?;
                         // We don't check condition before jumping because else doesn't have any condition. If this instruction gets executed, just perform jump. When generating bytecode for if condition, when if condition is false, jump has to be immediately after this opcode (total 3 bytes). Otherwise it will get messy.
-                        self.current_frame().ip_offset += offset as usize;
+                        self.jump_forward(offset)?;
                     }
                     OpCode::OpLoop => {
-                        let offset = self.current_frame().read_u16();
-                        self.current_frame().ip_offset -= offset as usize;
+                        let offset = self.read_u16()?;
+                        self.jump_back(offset)?;
                     }
                     OpCode::OpCall => self.op_call()?,
+                    OpCode::OpCheckType => self.op_check_type()?,
+                    OpCode::OpBuildArray => self.op_build_array()?,
+                    OpCode::OpUnpack => self.op_unpack()?,
                 }
+            } else {
+                // Malformed bytecode, carrying on would read operands as instructions
+                return Err(
+                    self.construct_runtime_error(format_args!("Invalid opcode {instruction_byte}"))
+                );
             }
         }
     }
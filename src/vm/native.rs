@@ -1,4 +1,7 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     value::{Value, objects::NativeFn},
@@ -8,24 +11,377 @@ use crate::{
 impl VM {
     pub(super) fn define_native(&mut self, name: &str, function: NativeFn) -> Result<(), VMError> {
         let val = Value::from_runtime_native(function, self)?;
-        self.globals.insert(name.to_owned(), val);
+        self.define_global(name, val);
         Ok(())
     }
 }
 
-pub(super) fn clock_native(_arg_count: u8, _values: Vec<Value>) -> Value {
+pub(super) fn clock_native(_vm: &mut VM, _values: Vec<Value>) -> Result<Value, VMError> {
     let now = SystemTime::now();
     let duration = now.duration_since(UNIX_EPOCH).expect("Time went backwards");
-    duration.as_secs_f64().into()
+    Ok(duration.as_secs_f64().into())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn println(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let mut line = values
+        .first()
+        .map(|value| value.format_with(vm.number_format))
+        .unwrap_or_default();
+    line.push('\n');
+    let result = vm.write_output(&line);
+    result.map_err(|e| vm.construct_runtime_error(format_args!("Can't print value: {e}")))?;
+
+    Ok(Value::new_nil())
+}
+
+/// Same as `println()`, but writes to error output, so diagnostics can be separated from normal output
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn eprint(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let result = if values.is_empty() {
+        writeln!(vm.error_output)
+    } else {
+        writeln!(
+            vm.error_output,
+            "{}",
+            values[0].format_with(vm.number_format)
+        )
+    };
+    result.map_err(|e| vm.construct_runtime_error(format_args!("Can't print value: {e}")))?;
+
+    Ok(Value::new_nil())
+}
+
+/// Returns the only argument of a native function, which should be a string
+fn string_argument<'a>(vm: &mut VM, name: &str, values: &'a [Value]) -> Result<&'a str, VMError> {
+    let [value] = values else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 1 arguments but got {} when calling '{name}'.",
+            values.len()
+        )));
+    };
+    string_value(vm, name, value)
+}
+
+/// Returns contents of an argument of a native function, which should be a string
+fn string_value<'a>(vm: &mut VM, name: &str, value: &'a Value) -> Result<&'a str, VMError> {
+    if !value.is_string() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "{name}() expects a string but got '{}'.",
+            value.type_name()
+        )));
+    }
+    Ok(value.as_string_ref())
+}
+
+/// Returns number of characters in a string. Characters written as escape sequences, like '\n' or '\u{1F600}', are counted once.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn len(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let string = string_argument(vm, "len", &values)?;
+    Ok(Value::from(string.chars().count() as f64))
+}
+
+/// Returns number of bytes a string takes when encoded as UTF-8
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn byte_len(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let string = string_argument(vm, "byte_len", &values)?;
+    Ok(Value::from(string.len() as f64))
+}
+
+/// Stops the script with a status code between 0 and 255.
+///
+/// Process isn't exited from here. `VMError::Exit` is returned instead, so the VM stops running and unwinds like it does for runtime errors.
+/// Then the host, like `run_file` or REPL, frees the memory, and the binary exits the process with the code.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn exit(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let [value] = values.as_slice() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 1 arguments but got {} when calling 'exit'.",
+            values.len()
+        )));
+    };
+
+    if value.is_number() {
+        let code = value.clone().to_number();
+        if code.fract() == 0.0 && (0.0..=255.0).contains(&code) {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            return Err(VMError::Exit(code as u8));
+        }
+    }
+
+    Err(vm.construct_runtime_error(format_args!(
+        "exit() expects a whole number between 0 and 255 but got '{}'.",
+        value.debug_repr()
+    )))
+}
+
+/// Returns a new string, made by replacing every `{}` in the first argument by the next argument.
+/// `{{` and `}}` are written as `{` and `}`. Number of placeholders should match number of remaining arguments.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn format(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let Some((template, arguments)) = values.split_first() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected at least 1 arguments but got 0 when calling 'format'."
+        )));
+    };
+    if !template.is_string() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "format() expects a string but got '{}'.",
+            template.type_name()
+        )));
+    }
+
+    let template = template.as_string_ref();
+    let placeholders = count_placeholders(template).map_err(|message| {
+        vm.construct_runtime_error(format_args!("Invalid format string: {message}"))
+    })?;
+    if placeholders != arguments.len() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "format() has {placeholders} placeholders but got {} arguments.",
+            arguments.len()
+        )));
+    }
+
+    // Template has been validated, so braces are either escaped or placeholders
+    let mut result = String::with_capacity(template.len());
+    let mut arguments = arguments.iter();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => {
+                let next = chars.next();
+                if c == '{' && next == Some('}') {
+                    // Safe to unwrap, number of arguments has been checked
+                    let argument = arguments.next().unwrap();
+                    result.push_str(&argument.format_with(vm.number_format));
+                } else {
+                    result.push(c);
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    Value::from_runtime_str(result, vm)
+}
+
+/// Counts `{}` in a format string. Returns error message if a brace is neither a placeholder nor escaped.
+fn count_placeholders(template: &str) -> Result<usize, &'static str> {
+    let mut count = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => count += 1,
+            ('{', Some('{')) | ('}', Some('}')) => {}
+            ('{', _) => return Err("unmatched '{'."),
+            ('}', _) => return Err("unmatched '}'."),
+            _ => continue,
+        }
+        // Second character of the pair
+        chars.next();
+    }
+    Ok(count)
+}
+
+/// Returns the smallest of two or more numbers. If any of them is `NaN`, result is `NaN`.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn min(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    extremum(vm, "min", &values, f64::min)
+}
+
+/// Does nothing if both arguments are equal, the same way `==` compares them. Otherwise it's a runtime error showing both values,
+/// so a Lox script can check its own results.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn assert_eq(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let [left, right] = values.as_slice() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 2 arguments but got {} when calling 'assert_eq'.",
+            values.len()
+        )));
+    };
+
+    if !vm.equals(left, right) {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Assertion failed: {} != {}",
+            left.debug_repr(),
+            right.debug_repr()
+        )));
+    }
+    Ok(Value::new_nil())
+}
+
+/// Returns true if an array has an element equal to the value, compared like `==` does, or if a string has the other string in it
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn contains(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let [collection, value] = values.as_slice() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 2 arguments but got {} when calling 'contains'.",
+            values.len()
+        )));
+    };
+
+    if collection.is_array() {
+        let found = collection
+            .as_array_ref()
+            .iter()
+            .any(|element| vm.equals(element, value));
+        return Ok(Value::from(found));
+    }
+    if !collection.is_string() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "contains() expects an array or a string but got '{}'.",
+            collection.type_name()
+        )));
+    }
+    if !value.is_string() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "contains() can only find a string in a string, but got '{}'.",
+            value.type_name()
+        )));
+    }
+    let found = collection.as_string_ref().contains(value.as_string_ref());
+    Ok(Value::from(found))
 }
 
+/// Returns an array of parts of a string, separated by the other string. Separators next to each other, or at either end, give
+/// empty strings, so there's always one more part than separators. Empty separator splits the string into characters.
 #[allow(clippy::needless_pass_by_value)]
-pub(super) fn println(_arg_count: u8, values: Vec<Value>) -> Value {
-    if values.is_empty() {
-        println!();
+pub(super) fn split(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let [string, separator] = values.as_slice() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 2 arguments but got {} when calling 'split'.",
+            values.len()
+        )));
+    };
+    let string = string_value(vm, "split", string)?;
+    let separator = string_value(vm, "split", separator)?;
+
+    let parts: Vec<String> = if separator.is_empty() {
+        string.chars().map(String::from).collect()
     } else {
-        println!("{}", values[0]);
+        string.split(separator).map(str::to_owned).collect()
+    };
+    // Parts and the array are created at runtime, so they should be tracked for garbage collection
+    let mut elements = Vec::with_capacity(parts.len());
+    for part in parts {
+        elements.push(Value::from_runtime_str(part, vm)?);
+    }
+    Value::from_runtime_array(elements, vm)
+}
+
+/// Returns a string made of elements of an array, with the separator between them. Elements which aren't strings are written
+/// the way `print` writes them.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn join(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let [array, separator] = values.as_slice() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 2 arguments but got {} when calling 'join'.",
+            values.len()
+        )));
+    };
+    if !array.is_array() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "join() expects an array but got '{}'.",
+            array.type_name()
+        )));
+    }
+    let separator = string_value(vm, "join", separator)?;
+
+    let mut joined = String::new();
+    for (index, element) in array.as_array_ref().iter().enumerate() {
+        if index > 0 {
+            joined.push_str(separator);
+        }
+        joined.push_str(&element.format_with(vm.number_format));
+    }
+    Value::from_runtime_str(joined, vm)
+}
+
+/// Returns the only argument as a string, written the way `print` writes it
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn str(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    let [value] = values.as_slice() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 1 arguments but got {} when calling 'str'.",
+            values.len()
+        )));
+    };
+    let text = value.format_with(vm.number_format);
+    Value::from_runtime_str(text, vm)
+}
+
+/// Starts capturing output of `print` and `println()` into a new buffer, until `capture_end()` is called. Captures can nest,
+/// output goes to the innermost one.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn capture_begin(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    if !values.is_empty() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 0 arguments but got {} when calling 'capture_begin'.",
+            values.len()
+        )));
+    }
+    vm.captures.push(String::new());
+    Ok(Value::new_nil())
+}
+
+/// Ends the innermost capture, and returns the output captured by it as a string. Captured output isn't written anywhere else.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn capture_end(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    if !values.is_empty() {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected 0 arguments but got {} when calling 'capture_end'.",
+            values.len()
+        )));
+    }
+    let Some(captured) = vm.captures.pop() else {
+        return Err(vm.construct_runtime_error(format_args!(
+            "capture_end() called without capture_begin()."
+        )));
+    };
+    Value::from_runtime_str(captured, vm)
+}
+
+/// Returns the largest of two or more numbers. If any of them is `NaN`, result is `NaN`.
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn max(vm: &mut VM, values: Vec<Value>) -> Result<Value, VMError> {
+    extremum(vm, "max", &values, f64::max)
+}
+
+/// Folds number arguments of `min()` or `max()` with `pick`, after checking there are at least two of them
+fn extremum(
+    vm: &mut VM,
+    name: &str,
+    values: &[Value],
+    pick: fn(f64, f64) -> f64,
+) -> Result<Value, VMError> {
+    if values.len() < 2 {
+        return Err(vm.construct_runtime_error(format_args!(
+            "Expected at least 2 arguments but got {} when calling '{name}'.",
+            values.len()
+        )));
+    }
+
+    let mut numbers = Vec::with_capacity(values.len());
+    for value in values {
+        if !value.is_number() {
+            return Err(vm.construct_runtime_error(format_args!(
+                "{name}() expects numbers but got '{}'.",
+                value.type_name()
+            )));
+        }
+        numbers.push(value.clone().to_number());
     }
 
-    Value::new_nil()
+    // `f64::min` and `f64::max` ignore `NaN`, but it should propagate, like it does for arithmetic
+    let result = numbers
+        .into_iter()
+        .reduce(|current, number| {
+            if current.is_nan() || number.is_nan() {
+                f64::NAN
+            } else {
+                pick(current, number)
+            }
+        })
+        .unwrap_or(f64::NAN);
+    Ok(Value::from(result))
 }
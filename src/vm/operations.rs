@@ -8,38 +8,15 @@ impl VM {
     /// This function concatenate strings and manage memory at runtime while doing so. If there are two literal strings in bytecode, concatenation will allocate memory for result, at runtime, and that value should be garbage collected
     fn concatenate_strings(
         &mut self,
-        left_operand: Value,
-        right_operand: Value,
+        left_operand: &Value,
+        right_operand: &Value,
     ) -> Result<(), VMError> {
-        // Check if left_operand is heap allocated string
-        let left = if left_operand.is_object() {
-            // Get reference to the `ObjectPointer` of `left_operand`
-            let left_pointer = left_operand.as_object_ref();
-            // Remove that pointer from linked list, because `Value` is going to be extracted
-            self.remove_object_pointer(left_pointer);
-            // Extract string from the pointer
-            left_operand.as_string()
-        } else {
-            // It's not heap allocated string, so just extract the value
-            left_operand.as_string()
-        };
-
-        // Check if right_operand is heap allocated string
-        let right = if right_operand.is_object() {
-            // Get reference to the `ObjectPointer` of `right_operand`
-            let right_pointer = right_operand.as_object_ref();
-            // Remove that pointer from linked list, because `Value` is going to be extracted
-            self.remove_object_pointer(right_pointer);
-            // Extract string from the pointer
-            right_operand.as_string()
-        } else {
-            // It's not heap allocated string, so just extract the value
-            right_operand.as_string()
-        };
+        // Operands are only read. Objects may still be referenced by variables, so they must stay owned by the VM.
+        let concatenated = format!("{left_operand}{right_operand}");
 
         // Because it's a runtime operation, being executed by vm, it needs to create a value
         // by using special functions. This is important for garbage collection.
-        let value = Value::from_runtime_str(left + &right, self)
+        let value = Value::from_runtime_str(concatenated, self)
             .map_err(|err| self.construct_runtime_error(format_args!("{err}")))?;
         self.push(value);
         // Return because our work here is done.
@@ -73,20 +50,33 @@ impl VM {
             // This will get executed if value is on stack
             .and_then(|val| {
                 let operands_are_numbers = right_operand.is_number() && val.is_number();
-                let one_operand_is_string = right_operand.is_string() || val.is_string();
-                // We're only interested if both operands are numbers or both are strings
-                if operands_are_numbers || (one_operand_is_string && opcode == &OpCode::OpAdd) {
+                let operands_are_arrays = right_operand.is_array() && val.is_array();
+                // An array can only be added to another array. It isn't converted to a string, even with a string operand.
+                let one_operand_is_array = right_operand.is_array() || val.is_array();
+                let one_operand_is_string =
+                    (right_operand.is_string() || val.is_string()) && !one_operand_is_array;
+                // We're only interested if both operands are numbers, or it's addition with a string or two arrays
+                if operands_are_numbers
+                    || (opcode == &OpCode::OpAdd && (one_operand_is_string || operands_are_arrays))
+                {
                     Ok(val)
                 } else {
                     // Invalid operation on operands, return error
-                    let err = format_args!("Invalid operation on these operands.");
-                    Err(self.construct_runtime_error(err))
+                    Err(self.construct_runtime_error(format_args!(
+                        "Invalid operation on operands of type '{}' and '{}'.",
+                        val.type_name(),
+                        right_operand.type_name()
+                    )))
                 }
             })?;
 
         // Concatinate if both operands are strings
         if right_operand.is_string() || left_operand.is_string() {
-            return self.concatenate_strings(left_operand, right_operand);
+            return self.concatenate_strings(&left_operand, &right_operand);
+        }
+        // Operands have been checked, so if one of them is an array, both are
+        if left_operand.is_array() {
+            return self.concatenate_arrays(&left_operand, &right_operand);
         }
 
         // Match the opcode and perform the relevant operation
@@ -97,6 +87,15 @@ impl VM {
             OpCode::OpSubtract => left_operand - right_operand,
             // Works because `Mul` trait is implemented
             OpCode::OpMultiply => left_operand * right_operand,
+            OpCode::OpDivide if self.integer_division => {
+                let (left, right) = (left_operand.to_number(), right_operand.to_number());
+                // Only whole numbers are divided as integers, `7.5 / 2` is still `3.75`
+                if left.fract() == 0.0 && right.fract() == 0.0 {
+                    (left / right).floor().into()
+                } else {
+                    (left / right).into()
+                }
+            }
             // Works because `Div` trait is implemented
             OpCode::OpDivide => left_operand / right_operand,
             // Checks if left > right
@@ -113,6 +112,16 @@ impl VM {
                 let res = left_operand.to_number() < right_operand.to_number();
                 res.into()
             }
+            // Checks if left >= right. Comparison with `NaN` is always false.
+            OpCode::OpGreaterEqual => {
+                let res = left_operand.to_number() >= right_operand.to_number();
+                res.into()
+            }
+            // Checks if left <= right. Comparison with `NaN` is always false.
+            OpCode::OpLessEqual => {
+                let res = left_operand.to_number() <= right_operand.to_number();
+                res.into()
+            }
             // This arm should never be matched.
             _ => unreachable!(),
         };
@@ -174,8 +183,18 @@ impl VM {
             let arguments = format_args!("Expected value on stack");
             self.construct_runtime_error(arguments)
         })?;
-        // This is possible because of PartialEq trait implementation
-        self.push((a == b).into());
+        let equal = self.equals(&a, &b);
+        self.push(equal.into());
         Ok(())
     }
+
+    /// Compares values like `==` does, following `loose_equality`. Contents are compared, so strings created at runtime are equal
+    /// to literal strings.
+    pub(super) fn equals(&self, a: &Value, b: &Value) -> bool {
+        if self.loose_equality {
+            a.loose_equals(b)
+        } else {
+            a.values_equal(b)
+        }
+    }
 }
@@ -0,0 +1,38 @@
+use crate::{
+    value::Value,
+    vm::{VM, VMError},
+};
+
+impl VM {
+    /// Pops the receiver and pushes value of its built-in property. There are no classes yet, so only strings and arrays have properties.
+    pub(super) fn op_get_property(&mut self) -> Result<(), VMError> {
+        // Read the property name from bytecode and convert it to literal string
+        let name = self.read_string_constant()?;
+        let receiver = self.pop().ok_or_else(|| {
+            self.construct_runtime_error(format_args!("Expected value on the stack"))
+        })?;
+
+        let value = match name.as_str() {
+            // Counts characters, like `len()` does, not bytes
+            "length" if receiver.is_string() => {
+                Value::from(receiver.as_string_ref().chars().count() as f64)
+            }
+            "length" if receiver.is_array() => Value::from(receiver.as_array_ref().len() as f64),
+            _ if receiver.is_string() || receiver.is_array() => {
+                return Err(self.construct_runtime_error(format_args!(
+                    "Undefined property '{name}' on '{}'.",
+                    receiver.type_name()
+                )));
+            }
+            _ => {
+                return Err(self.construct_runtime_error(format_args!(
+                    "Only strings and arrays have properties, '{}' doesn't.",
+                    receiver.type_name()
+                )));
+            }
+        };
+
+        self.push(value);
+        Ok(())
+    }
+}
@@ -0,0 +1,873 @@
+use crate::{
+    chunk::{Chunk, OpCode},
+    constants::FRAMES_MAX,
+    test_utils::SharedBuffer,
+    value::{Value, objects::FunctionObject},
+    vm::{VM, errors::VMError},
+};
+
+/// Runs hand written bytecode as a top-level script. Function isn't pushed onto the stack if `push_function` is false, to test instructions with an empty stack.
+fn run_chunk(chunk: Chunk, push_function: bool) -> Result<Value, VMError> {
+    let mut vm = VM::new();
+    if push_function {
+        return vm.run_chunk(chunk);
+    }
+    let mut function = FunctionObject::new();
+    function.chunk = chunk;
+    vm.call(function.into(), 0)?;
+    vm.interpret()
+}
+
+fn chunk_from(code: &[u8]) -> Chunk {
+    let mut chunk = Chunk::new();
+    for byte in code {
+        chunk.write_chunk(*byte, 1);
+    }
+    chunk
+}
+
+#[test]
+fn get_local_with_invalid_slot_is_runtime_error() {
+    let chunk = chunk_from(&[OpCode::OpGetLocal as u8, 5, OpCode::OpReturn as u8]);
+    let result = run_chunk(chunk, true);
+    assert!(
+        matches!(result, Err(VMError::RuntimeError(e)) if e.contains("Invalid local variable slot 5"))
+    );
+}
+
+#[test]
+fn set_local_with_invalid_slot_is_runtime_error() {
+    let chunk = chunk_from(&[
+        OpCode::OpNil as u8,
+        OpCode::OpSetLocal as u8,
+        9,
+        OpCode::OpReturn as u8,
+    ]);
+    let result = run_chunk(chunk, true);
+    assert!(
+        matches!(result, Err(VMError::RuntimeError(e)) if e.contains("Invalid local variable slot 9"))
+    );
+}
+
+#[test]
+fn return_with_empty_stack_is_runtime_error() {
+    let chunk = chunk_from(&[OpCode::OpReturn as u8]);
+    let result = run_chunk(chunk, false);
+    assert!(matches!(result, Err(VMError::RuntimeError(e)) if e.contains("Expected return value")));
+}
+
+#[test]
+fn conditional_jump_with_empty_stack_is_runtime_error() {
+    for opcode in [OpCode::OpJumpIfFalse, OpCode::OpJumpIfNotNil] {
+        let chunk = chunk_from(&[opcode as u8, 0, 0, OpCode::OpReturn as u8]);
+        let result = run_chunk(chunk, false);
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with("Expected value on the stack")),
+            "{result:?}"
+        );
+    }
+}
+
+#[test]
+fn reset_frees_runtime_objects_on_stack_once() {
+    let mut vm = VM::new();
+    let value = Value::from_runtime_str("runtime".to_owned(), &mut vm).unwrap();
+    // Same object is owned by `objects` list and referenced twice from the stack
+    vm.push(value.clone());
+    vm.push(value);
+
+    vm.reset_vm();
+
+    assert!(vm.stack.is_empty());
+    assert!(vm.objects.is_none());
+}
+
+#[test]
+fn run_without_frames_is_runtime_error() {
+    let mut vm = VM::new();
+    let result = vm.run();
+    assert!(matches!(result, Err(VMError::RuntimeError(e)) if e.contains("No active call frame.")));
+}
+
+#[test]
+fn print_writes_to_output_without_newline() {
+    let mut vm = VM::new();
+    let buffer = SharedBuffer::default();
+    vm.set_output(buffer.clone());
+
+    crate::interpret(r#"print 1; print "two"; print nil;"#, &mut vm).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer.0.borrow().clone()).unwrap(),
+        "1twonil"
+    );
+    vm.reset_vm();
+}
+
+#[test]
+fn dup_pushes_copy_of_top_value() {
+    let mut chunk = chunk_from(&[
+        OpCode::OpConstant as u8,
+        0,
+        OpCode::OpDup as u8,
+        OpCode::OpMultiply as u8,
+        OpCode::OpReturn as u8,
+    ]);
+    chunk.add_constant(Value::from(3.0)).unwrap();
+    let result = run_chunk(chunk, true);
+    assert_eq!(result.unwrap(), Value::from(9.0));
+}
+
+#[test]
+fn dup_with_empty_stack_is_runtime_error() {
+    let chunk = chunk_from(&[OpCode::OpDup as u8, OpCode::OpReturn as u8]);
+    let result = run_chunk(chunk, false);
+    assert!(
+        matches!(result, Err(VMError::RuntimeError(e)) if e.contains("Expected value on the stack"))
+    );
+}
+
+#[test]
+fn tuck_copies_top_value_below_second() {
+    // [2, 3] -> [3, 2, 3], then 2 - 3 leaves [3, -1], and 3 * -1 is -3
+    let mut chunk = chunk_from(&[
+        OpCode::OpConstant as u8,
+        0,
+        OpCode::OpConstant as u8,
+        1,
+        OpCode::OpTuck as u8,
+        OpCode::OpSubtract as u8,
+        OpCode::OpMultiply as u8,
+        OpCode::OpReturn as u8,
+    ]);
+    chunk.add_constant(Value::from(2.0)).unwrap();
+    chunk.add_constant(Value::from(3.0)).unwrap();
+    assert_eq!(run_chunk(chunk, true).unwrap(), Value::from(-3.0));
+
+    let chunk = chunk_from(&[OpCode::OpTuck as u8, OpCode::OpReturn as u8]);
+    let result = run_chunk(chunk, false);
+    assert!(
+        matches!(result, Err(VMError::RuntimeError(e)) if e.contains("Expected two values on the stack"))
+    );
+}
+
+#[test]
+fn unnamed_function_frames_are_anonymous_in_trace() {
+    let anonymous: Value = {
+        let mut function = FunctionObject::new();
+        function.chunk = chunk_from(&[
+            OpCode::OpNil as u8,
+            OpCode::OpNegate as u8,
+            OpCode::OpReturn as u8,
+        ]);
+        function.into()
+    };
+    let mut chunk = chunk_from(&[
+        OpCode::OpConstant as u8,
+        0,
+        OpCode::OpCall as u8,
+        0,
+        OpCode::OpReturn as u8,
+    ]);
+    chunk.add_constant(anonymous).unwrap();
+
+    let result = run_chunk(chunk, true);
+    assert!(
+        matches!(result, Err(VMError::RuntimeError(e)) if e.ends_with("[line 1] in <anonymous fn>\n[line 1] in <script>\n"))
+    );
+}
+
+#[test]
+fn pop_n_truncates_stack() {
+    let mut chunk = chunk_from(&[
+        OpCode::OpConstant as u8,
+        0,
+        OpCode::OpNil as u8,
+        OpCode::OpNil as u8,
+        OpCode::OpPopN as u8,
+        2,
+        OpCode::OpReturn as u8,
+    ]);
+    chunk.add_constant(Value::from(1.0)).unwrap();
+    assert_eq!(run_chunk(chunk, true).unwrap(), Value::from(1.0));
+
+    let chunk = chunk_from(&[OpCode::OpPopN as u8, 3, OpCode::OpReturn as u8]);
+    let result = run_chunk(chunk, true);
+    assert!(
+        matches!(result, Err(VMError::RuntimeError(e)) if e.contains("Expected 3 values on the stack"))
+    );
+}
+
+#[test]
+fn eprint_writes_to_error_output() {
+    let mut vm = VM::new();
+    let output = SharedBuffer::default();
+    let error_output = SharedBuffer::default();
+    vm.set_output(output.clone());
+    vm.set_error_output(error_output.clone());
+
+    crate::interpret(r#"println("out"); eprint("err"); eprint();"#, &mut vm).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.0.borrow().clone()).unwrap(),
+        "out\n"
+    );
+    assert_eq!(
+        String::from_utf8(error_output.0.borrow().clone()).unwrap(),
+        "err\n\n"
+    );
+    vm.reset_vm();
+}
+
+#[test]
+fn calling_non_callable_is_runtime_error() {
+    let sources = [
+        ("3();", "'number'"),
+        ("\"s\"(1, 2);", "'string'"),
+        ("var x = nil;\nx();", "'nil'"),
+    ];
+    for (source, type_name) in sources {
+        let mut vm = VM::new();
+        let result = crate::interpret(source, &mut vm);
+        let expected = format!("Can only call functions, {type_name} is not callable.\n");
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with(&expected) && e.ends_with("in <script>\n")),
+            "{result:?}"
+        );
+        assert!(vm.stack.is_empty());
+        vm.reset_vm();
+    }
+}
+
+/// Operators on `Value` panic for non-number operands, VM should check operand types before using them
+#[test]
+fn operators_on_non_numbers_are_runtime_errors() {
+    let binary_ops = [
+        OpCode::OpAdd as u8,
+        OpCode::OpSubtract as u8,
+        OpCode::OpMultiply as u8,
+        OpCode::OpDivide as u8,
+        OpCode::OpGreater as u8,
+        OpCode::OpLess as u8,
+        OpCode::OpGreaterEqual as u8,
+        OpCode::OpLessEqual as u8,
+    ];
+    let operands = [
+        (OpCode::OpNil as u8, None, "nil"),
+        (OpCode::OpTrue as u8, None, "bool"),
+        (OpCode::OpConstant as u8, Some(Value::from(1.0)), "number"),
+        (
+            OpCode::OpConstant as u8,
+            Some(Value::from("s".to_owned())),
+            "string",
+        ),
+    ];
+
+    for opcode in binary_ops {
+        for (left_op, left, left_ty) in &operands {
+            for (right_op, right, right_ty) in &operands {
+                let numbers = *left_ty == "number" && *right_ty == "number";
+                let concatenation = opcode == OpCode::OpAdd as u8
+                    && (*left_ty == "string" || *right_ty == "string");
+                if numbers || concatenation {
+                    continue;
+                }
+
+                let mut chunk = Chunk::new();
+                for (op, value) in [(left_op, left), (right_op, right)] {
+                    chunk.write_chunk(*op, 1);
+                    if let Some(value) = value {
+                        let index = chunk.add_constant(value.clone()).unwrap();
+                        chunk.write_chunk(index, 1);
+                    }
+                }
+                chunk.write_chunk(opcode, 1);
+                chunk.write_chunk(OpCode::OpReturn as u8, 1);
+
+                let expected =
+                    format!("Invalid operation on operands of type '{left_ty}' and '{right_ty}'.");
+                let result = run_chunk(chunk, true);
+                assert!(
+                    matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with(&expected)),
+                    "{opcode:?}: {result:?}"
+                );
+            }
+        }
+    }
+
+    for (op, value, _) in &operands[..2] {
+        assert!(value.is_none());
+        let chunk = chunk_from(&[*op, OpCode::OpNegate as u8, OpCode::OpReturn as u8]);
+        let result = run_chunk(chunk, true);
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with("Operand must be a number.")),
+            "{result:?}"
+        );
+    }
+
+    let mut chunk = Chunk::new();
+    let index = chunk.add_constant(Value::from(1.0)).unwrap();
+    for byte in [
+        OpCode::OpConstant as u8,
+        index,
+        OpCode::OpNot as u8,
+        OpCode::OpReturn as u8,
+    ] {
+        chunk.write_chunk(byte, 1);
+    }
+    let result = run_chunk(chunk, true);
+    assert!(
+        matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with("Operand of ! operator should be a bool or nil")),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn frames_are_allocated_lazily() {
+    let vm = VM::new();
+    assert_eq!(vm.frames.capacity(), 0);
+
+    // Calls can't go deeper than `FRAMES_MAX`, so reserving more would be a waste
+    let vm = VM::with_frames_capacity(FRAMES_MAX * 2);
+    assert_eq!(vm.frames.capacity(), FRAMES_MAX);
+
+    // Frames still grow when a lazily allocated VM calls functions
+    let mut vm = VM::new();
+    let result = crate::interpret(
+        "fun f(n) { if (n == 0) return 0; return f(n - 1); } f(10)",
+        &mut vm,
+    );
+    assert_eq!(result.unwrap(), Value::from(0.0));
+    vm.reset_vm();
+}
+
+#[test]
+fn run_chunk_runs_hand_written_bytecode() {
+    let mut vm = VM::new();
+
+    // -(2 * 3)
+    let mut chunk = Chunk::new();
+    let two = chunk.add_constant(Value::from(2.0)).unwrap();
+    let three = chunk.add_constant(Value::from(3.0)).unwrap();
+    for byte in [
+        OpCode::OpConstant as u8,
+        two,
+        OpCode::OpConstant as u8,
+        three,
+        OpCode::OpMultiply as u8,
+        OpCode::OpNegate as u8,
+        OpCode::OpReturn as u8,
+    ] {
+        chunk.write_chunk(byte, 1);
+    }
+    assert_eq!(vm.run_chunk(chunk).unwrap(), Value::from(-6.0));
+
+    // Globals defined by one chunk are visible to the next one
+    let mut chunk = Chunk::new();
+    let name = chunk
+        .add_constant(Value::from("answer".to_owned()))
+        .unwrap();
+    let value = chunk.add_constant(Value::from(42.0)).unwrap();
+    for byte in [
+        OpCode::OpConstant as u8,
+        value,
+        OpCode::OpDefineGlobal as u8,
+        name,
+        OpCode::OpReturnNil as u8,
+    ] {
+        chunk.write_chunk(byte, 1);
+    }
+    assert!(vm.run_chunk(chunk).unwrap().is_nil());
+
+    let mut chunk = Chunk::new();
+    let name = chunk
+        .add_constant(Value::from("answer".to_owned()))
+        .unwrap();
+    for byte in [OpCode::OpGetGlobal as u8, name, OpCode::OpReturn as u8] {
+        chunk.write_chunk(byte, 1);
+    }
+    assert_eq!(vm.run_chunk(chunk).unwrap(), Value::from(42.0));
+    vm.reset_vm();
+}
+
+#[test]
+fn run_chunk_recovers_after_runtime_error() {
+    let mut vm = VM::new();
+    // Negating `nil` fails, and leaves the function on the stack
+    let chunk = chunk_from(&[
+        OpCode::OpNil as u8,
+        OpCode::OpNegate as u8,
+        OpCode::OpReturn as u8,
+    ]);
+    let result = vm.run_chunk(chunk);
+    assert!(
+        matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with("Operand must be a number.")),
+        "{result:?}"
+    );
+
+    let chunk = chunk_from(&[OpCode::OpTrue as u8, OpCode::OpReturn as u8]);
+    assert_eq!(vm.run_chunk(chunk).unwrap(), Value::from(true));
+    assert!(vm.stack.is_empty());
+    vm.reset_vm();
+}
+
+#[test]
+fn integer_division_floors_whole_numbers() {
+    let source = "7 / 2";
+    let mut vm = VM::new();
+    // Default division is floating-point
+    assert_eq!(crate::interpret(source, &mut vm).unwrap(), Value::from(3.5));
+
+    vm.integer_division = true;
+    for (source, expected) in [
+        ("7 / 2", 3.0),
+        ("6 / 3", 2.0),
+        ("-7 / 2", -4.0),
+        ("7.5 / 2", 3.75),
+        ("7 / 0.5", 14.0),
+        ("1 / 0", f64::INFINITY),
+    ] {
+        assert_eq!(
+            crate::interpret(source, &mut vm).unwrap(),
+            Value::from(expected),
+            "{source}"
+        );
+    }
+    vm.reset_vm();
+}
+
+#[test]
+fn loose_equality_is_opt_in() {
+    let mut vm = VM::new();
+    let source = r#"1 == "1" and !(1 != "1")"#;
+    assert_eq!(
+        crate::interpret(source, &mut vm).unwrap(),
+        Value::from(false)
+    );
+
+    vm.loose_equality = true;
+    assert_eq!(
+        crate::interpret(source, &mut vm).unwrap(),
+        Value::from(true)
+    );
+    // Strings created at runtime are coerced too
+    let source = r#"var n = "4" + "2"; n == 42 and 42 == n and n != 41"#;
+    assert_eq!(
+        crate::interpret(source, &mut vm).unwrap(),
+        Value::from(true)
+    );
+    vm.reset_vm();
+}
+
+#[test]
+fn corrupt_jump_offsets_are_runtime_errors() {
+    let cases = [
+        (
+            vec![OpCode::OpJump as u8, 0xff, 0xff, OpCode::OpReturnNil as u8],
+            "Jump target 65538 is past the end of bytecode of length 4.",
+        ),
+        (
+            vec![
+                OpCode::OpFalse as u8,
+                OpCode::OpPopJumpIfFalse as u8,
+                0,
+                10,
+                OpCode::OpReturnNil as u8,
+            ],
+            "Jump target 14 is past the end of bytecode of length 5.",
+        ),
+        (
+            vec![
+                OpCode::OpFalse as u8,
+                OpCode::OpJumpIfFalse as u8,
+                0,
+                10,
+                OpCode::OpReturnNil as u8,
+            ],
+            "Jump target 14 is past the end of bytecode of length 5.",
+        ),
+        (
+            vec![OpCode::OpNil as u8, OpCode::OpLoop as u8, 1, 0],
+            "Loop offset 256 jumps before start of bytecode from position 4.",
+        ),
+    ];
+    for (code, expected) in cases {
+        let result = run_chunk(chunk_from(&code), true);
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with(expected)),
+            "{result:?}"
+        );
+    }
+
+    // Jumps inside the bytecode still work, skipping `OpNil` here
+    let chunk = chunk_from(&[
+        OpCode::OpTrue as u8,
+        OpCode::OpJump as u8,
+        0,
+        1,
+        OpCode::OpNil as u8,
+        OpCode::OpReturn as u8,
+    ]);
+    assert_eq!(run_chunk(chunk, true).unwrap(), Value::from(true));
+}
+
+#[test]
+fn malformed_bytecode_is_runtime_error() {
+    let cases: [(&[u8], &str); 6] = [
+        // Missing trailing return
+        (
+            &[OpCode::OpNil as u8],
+            "Reached end of bytecode without return.",
+        ),
+        (&[], "Reached end of bytecode without return."),
+        // Operands cut off by the end of bytecode
+        (
+            &[OpCode::OpJump as u8, 0],
+            "Reached end of bytecode without return.",
+        ),
+        (
+            &[OpCode::OpConstant as u8],
+            "Reached end of bytecode without return.",
+        ),
+        (
+            &[OpCode::OpConstant as u8, 3, OpCode::OpReturn as u8],
+            "Constant index 3 is out of bounds of constant pool of size 0.",
+        ),
+        // Byte which isn't an opcode isn't skipped
+        (
+            &[250, OpCode::OpNil as u8, OpCode::OpReturn as u8],
+            "Invalid opcode 250",
+        ),
+    ];
+    for (code, expected) in cases {
+        let result = run_chunk(chunk_from(code), true);
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with(expected)),
+            "{code:?}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn name_operand_which_isnt_a_string_is_runtime_error() {
+    let cases: [(&[u8], &str); 5] = [
+        (
+            &[OpCode::OpNil as u8, OpCode::OpDefineGlobal as u8, 0],
+            "Expected a string constant but got 'number'.",
+        ),
+        (
+            &[OpCode::OpNil as u8, OpCode::OpSetGlobal as u8, 0],
+            "Expected a string constant but got 'number'.",
+        ),
+        (
+            &[OpCode::OpGetGlobal as u8, 0],
+            "Expected name of global variable in constant 0",
+        ),
+        (
+            &[OpCode::OpNil as u8, OpCode::OpGetProperty as u8, 0],
+            "Expected a string constant but got 'number'.",
+        ),
+        (
+            &[OpCode::OpCheckType as u8, 0, 0],
+            "Expected a string constant but got 'number'.",
+        ),
+    ];
+    for (code, expected) in cases {
+        let mut chunk = chunk_from(code);
+        chunk.add_constant(Value::from(1.0)).unwrap();
+        chunk.write_chunk(OpCode::OpReturn as u8, 1);
+        let result = run_chunk(chunk, true);
+        assert!(
+            matches!(&result, Err(VMError::RuntimeError(e)) if e.starts_with(expected)),
+            "{code:?}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn global_reads_are_cached_by_each_vm() {
+    let mut chunk = Chunk::new();
+    let name = chunk.add_constant(Value::from("step".to_owned())).unwrap();
+    for byte in [OpCode::OpGetGlobal as u8, name, OpCode::OpReturn as u8] {
+        chunk.write_chunk(byte, 1);
+    }
+    let mut function = FunctionObject::new();
+    function.chunk = chunk.clone();
+    let function = Value::from(function);
+
+    let mut first = VM::new();
+    first.define_global("step", Value::from(2.0));
+    assert_eq!(
+        first.run_isolated(function.clone()).unwrap(),
+        Value::from(2.0)
+    );
+    // Bytecode isn't rewritten, slot is only cached by the VM
+    assert_eq!(function.as_function_ref().chunk, chunk);
+
+    // Same function on another VM, where `step` is in a different slot
+    let mut second = VM::new();
+    second.define_global("other", Value::from(1.0));
+    second.define_global("step", Value::from(99.0));
+    assert_ne!(first.globals["step"], second.globals["step"]);
+    assert_eq!(
+        second.run_isolated(function.clone()).unwrap(),
+        Value::from(99.0)
+    );
+
+    // Cached slot keeps reading redefined value
+    first.define_global("step", Value::from(3.0));
+    assert_eq!(
+        first.run_isolated(function.clone()).unwrap(),
+        Value::from(3.0)
+    );
+    // Function isn't owned by either VM, it's freed once
+    first.free_script(function);
+    first.reset_vm();
+    second.reset_vm();
+}
+
+#[test]
+fn compiled_script_reads_globals_of_vm_running_it() {
+    let script = Value::from(crate::compile("a * 100 + b").unwrap());
+    let mut first = VM::new();
+    let mut second = VM::new();
+    crate::interpret("var a = 1; var b = 1;", &mut first).unwrap();
+    crate::interpret("var c = 0; var b = 99; var a = 2;", &mut second).unwrap();
+    for _ in 0..2 {
+        assert_eq!(
+            first.run_isolated(script.clone()).unwrap(),
+            Value::from(101.0)
+        );
+        assert_eq!(
+            second.run_isolated(script.clone()).unwrap(),
+            Value::from(299.0)
+        );
+    }
+    // Script isn't owned by either VM, it's freed once
+    first.free_script(script);
+    first.reset_vm();
+    second.reset_vm();
+}
+
+#[test]
+fn cached_global_reads_see_assignments() {
+    let mut vm = VM::new();
+    let result = crate::interpret(
+        "var total = 0; var step = 1; \
+         for (var i = 0; i < 4; i = i + 1) { total = total + step; step = step * 2; } \
+         total",
+        &mut vm,
+    );
+    assert_eq!(result.unwrap(), Value::from(15.0));
+    vm.reset_vm();
+}
+
+#[test]
+fn cached_global_reads_are_evicted_with_their_script() {
+    let mut vm = VM::new();
+    crate::interpret("var a = 1;", &mut vm).unwrap();
+    for _ in 0..10 {
+        crate::interpret("a + a;", &mut vm).unwrap();
+    }
+    assert!(vm.global_cache.is_empty());
+
+    // Functions declared by a script outlive it, so their reads stay cached
+    crate::interpret("fun f() { return a; } f();", &mut vm).unwrap();
+    assert_eq!(vm.global_cache.len(), 1);
+    assert_eq!(crate::interpret("f()", &mut vm).unwrap(), Value::from(1.0));
+    assert_eq!(vm.global_cache.len(), 1);
+    vm.reset_vm();
+}
+
+#[test]
+fn print_evaluates_operands_once_and_short_circuits() {
+    let cases = [
+        // Right operand of `and` isn't evaluated when left one is falsey
+        ("print false and effect();", "false", 0.0),
+        ("print true and effect();", "e1", 1.0),
+        // Right operand of `or` isn't evaluated when left one is truthy
+        ("print 1 or effect();", "1", 0.0),
+        ("print nil or effect();", "e1", 1.0),
+        ("print nil ?? effect();", "e1", 1.0),
+        ("print 0 ?? effect();", "0", 0.0),
+        // Precedence: `and` binds tighter than `or`
+        ("print false and effect() or effect();", "e1", 1.0),
+        ("print effect() or effect() and effect();", "e1", 1.0),
+        // Assignment is evaluated once and prints the assigned value
+        ("var x; print x = effect();", "e1", 1.0),
+        ("print effect() + effect();", "e1e2", 2.0),
+    ];
+    for (code, expected, calls) in cases {
+        let mut vm = VM::new();
+        let buffer = SharedBuffer::default();
+        vm.set_output(buffer.clone());
+
+        let code = format!(
+            r#"var calls = 0; fun effect() {{ calls = calls + 1; return "e" + calls; }} {code} calls"#
+        );
+        let result = crate::interpret(&code, &mut vm).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer.0.borrow().clone()).unwrap(),
+            expected,
+            "{code}"
+        );
+        assert_eq!(result, Value::from(calls), "{code}");
+        vm.reset_vm();
+    }
+}
+
+#[test]
+fn adding_empty_arrays() {
+    let mut vm = VM::new();
+    let mut chunk = Chunk::new();
+    let one = chunk.add_constant(Value::from(1.0)).unwrap();
+    // [] + [1] + []
+    for byte in [
+        OpCode::OpBuildArray as u8,
+        0,
+        OpCode::OpConstant as u8,
+        one,
+        OpCode::OpBuildArray as u8,
+        1,
+        OpCode::OpAdd as u8,
+        OpCode::OpBuildArray as u8,
+        0,
+        OpCode::OpAdd as u8,
+        OpCode::OpReturn as u8,
+    ] {
+        chunk.write_chunk(byte, 1);
+    }
+    let result = vm.run_chunk(chunk).unwrap();
+    assert_eq!(result.as_array_ref(), &[Value::from(1.0)]);
+
+    // [] + []
+    let chunk = chunk_from(&[
+        OpCode::OpBuildArray as u8,
+        0,
+        OpCode::OpBuildArray as u8,
+        0,
+        OpCode::OpAdd as u8,
+        OpCode::OpReturn as u8,
+    ]);
+    let result = vm.run_chunk(chunk).unwrap();
+    assert!(result.is_array() && result.as_array_ref().is_empty());
+    vm.reset_vm();
+}
+
+#[test]
+fn number_format_applies_to_printed_values() {
+    use crate::value::NumberFormat;
+
+    let code = r#"
+print 1.5;
+print " ";
+print [1, [2.25]];
+println();
+println(3);
+print str(0.5) + " " + format("{}", 4) + " " + join([1, 2], ",");
+println();
+print "sum " + 1.5;
+"#;
+    let cases = [
+        (
+            NumberFormat::Default,
+            "1.5 [1, [2.25]]\n3\n0.5 4 1,2\nsum 1.5",
+        ),
+        (
+            NumberFormat::Fixed(2),
+            "1.50 [1.00, [2.25]]\n3.00\n0.50 4.00 1.00,2.00\nsum 1.5",
+        ),
+        (
+            NumberFormat::Scientific,
+            "1.5e0 [1e0, [2.25e0]]\n3e0\n5e-1 4e0 1e0,2e0\nsum 1.5",
+        ),
+    ];
+    for (number_format, expected) in cases {
+        let mut vm = VM::new();
+        vm.number_format = number_format;
+        let buffer = SharedBuffer::default();
+        vm.set_output(buffer.clone());
+
+        crate::interpret(code, &mut vm).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer.0.borrow().clone()).unwrap(),
+            expected,
+            "{number_format:?}"
+        );
+        vm.reset_vm();
+    }
+}
+
+#[test]
+fn finished_program_leaves_no_frames() {
+    let mut vm = VM::new();
+    // Runs a program without `run_isolated`, which would clean up after it anyway
+    let run = |vm: &mut VM, code: &str| {
+        let function = Value::from(crate::compile(code).unwrap());
+        vm.push(function.clone());
+        vm.call(function.clone(), 0).unwrap();
+        let result = vm.interpret();
+        vm.free_script(function);
+        result
+    };
+
+    let result = run(&mut vm, "var x = 1; fun f(a) { return a + x; } f(1)");
+    assert_eq!(result.unwrap(), Value::from(2.0));
+    assert!(vm.frames.is_empty());
+    assert!(vm.stack.is_empty());
+
+    // Second program on the same VM starts from a clean stack and frames, and sees globals of the first one
+    let result = run(&mut vm, "{ var y = 10; x = f(y); } x");
+    assert_eq!(result.unwrap(), Value::from(11.0));
+    assert!(vm.frames.is_empty());
+    assert!(vm.stack.is_empty());
+
+    // Implicit return of a script ends it the same way
+    assert!(run(&mut vm, "x = 3;").unwrap().is_nil());
+    assert!(vm.frames.is_empty() && vm.stack.is_empty());
+    vm.reset_vm();
+}
+
+#[test]
+fn failed_script_ends_its_captures() {
+    let mut vm = VM::new();
+    let buffer = SharedBuffer::default();
+    vm.set_output(buffer.clone());
+
+    // Capture is never ended, because script fails
+    let result = crate::interpret("capture_begin(); print 1; nil + 1;", &mut vm);
+    assert!(result.is_err());
+    crate::interpret("print 2; capture_begin(); print 3;", &mut vm).unwrap();
+    // Capture of a successful script lasts, like globals do, until it's ended
+    let captured = crate::interpret("print 4; capture_end()", &mut vm).unwrap();
+
+    assert_eq!(captured.to_string(), "34");
+    assert_eq!(String::from_utf8(buffer.0.borrow().clone()).unwrap(), "2");
+    vm.reset_vm();
+}
+
+#[test]
+fn errors_map_to_sysexits_codes() {
+    use crate::{compiler::errors::CompilerError, constants::ExitCode};
+
+    let compile_error = VMError::CompileError(CompilerError::ExpressionError(
+        "Expect expression.".to_owned(),
+    ));
+    assert_eq!(compile_error.exit_code(), 65);
+    assert_eq!(compile_error.exit_code(), ExitCode::CompileError.into());
+    assert_eq!(VMError::RuntimeError(String::new()).exit_code(), 70);
+    assert_eq!(
+        VMError::RuntimeError(String::new()).exit_code(),
+        ExitCode::RuntimeError.into()
+    );
+    assert_eq!(VMError::IoError(String::new()).exit_code(), 74);
+    assert_eq!(
+        VMError::IoError(String::new()).exit_code(),
+        ExitCode::IoError.into()
+    );
+    // Code passed to `exit()` is kept as it is, even 0
+    assert_eq!(VMError::Exit(0).exit_code(), 0);
+    assert_eq!(VMError::Exit(3).exit_code(), 3);
+}
@@ -1,49 +1,134 @@
-use crate::vm::{VM, VMError};
+use std::ptr;
+
+use crate::{
+    value::Value,
+    vm::{VM, VMError},
+};
 
 impl VM {
-    pub(super) fn op_get_local(&mut self) {
-        let slot = self.current_frame().read_byte();
-        let index = self.current_frame().starting_offset + slot as usize;
-        let val = self.stack[index].clone();
+    /// Reads the local variable from its slot in current frame and pushes it onto the stack
+    pub(super) fn op_get_local(&mut self) -> Result<(), VMError> {
+        let slot = self.read_byte()?;
+        let index = self.current_frame()?.starting_offset + slot as usize;
+        // Malformed bytecode can point outside the stack, return error instead of panicking
+        let val = self.stack.get(index).cloned().ok_or_else(|| {
+            self.construct_runtime_error(format_args!("Invalid local variable slot {slot}"))
+        })?;
         self.push(val);
+        Ok(())
+    }
+
+    /// Assigns the value at the top of the stack to the local variable's slot
+    pub(super) fn op_set_local(&mut self) -> Result<(), VMError> {
+        let slot = self.read_byte()?;
+        let val = self.stack.last().cloned().ok_or_else(|| {
+            self.construct_runtime_error(format_args!("Expected value on the stack"))
+        })?;
+        let index = self.current_frame()?.starting_offset + slot as usize;
+        // Slot should already exist on the stack, it's created when local variable is declared
+        let Some(local) = self.stack.get_mut(index) else {
+            return Err(
+                self.construct_runtime_error(format_args!("Invalid local variable slot {slot}"))
+            );
+        };
+        *local = val;
+        Ok(())
+    }
+    /// Returns value of a global variable, if it's defined
+    pub(super) fn global(&self, name: &str) -> Option<&Value> {
+        self.globals
+            .get(name)
+            .map(|&slot| &self.global_values[slot])
     }
-    pub(super) fn op_set_local(&mut self) {
-        let slot = self.current_frame().read_byte();
-        let val = self.stack[self.stack.len() - 1].clone();
-        let index = self.current_frame().starting_offset + slot as usize;
-        self.replace_or_push(val, index);
+
+    /// Defines a global variable. If it's already defined, value is replaced in the same slot, so cached slots stay valid.
+    pub(super) fn define_global(&mut self, name: &str, value: Value) {
+        if let Some(&slot) = self.globals.get(name) {
+            self.global_values[slot] = value;
+        } else {
+            self.globals
+                .insert(name.to_owned(), self.global_values.len());
+            self.global_values.push(value);
+            self.global_names.push(name.to_owned());
+        }
     }
+
     /// Define a global variable and insert into `HashMap`
     pub(super) fn op_define_global(&mut self) -> Result<(), VMError> {
         // Read the variable name from bytecode and convert it to literal string
-        let name = self.current_frame().read_constant().as_string();
+        let name = self.read_string_constant()?;
         // If variable is not initilized, default value stored on stack should be `Nil`. In both cases, we're expecting value on the stack.
         let value= self.pop().ok_or_else(||
                             // Return error if value on stack is not found
                             self.construct_runtime_error(format_args!("Expected value on the stack")))?;
+        if self.strict_globals
+            && let Some(existing) = self.global(&name)
+        {
+            let error = if existing.is_native() {
+                self.construct_runtime_error(format_args!(
+                    "Can't redefine native function '{name}'."
+                ))
+            } else {
+                self.construct_runtime_error(format_args!("Global '{name}' is already defined."))
+            };
+            return Err(error);
+        }
         // Insert variable's name and value into `HashMap`
-        self.globals.insert(name, value);
+        self.define_global(&name, value);
         Ok(())
     }
 
-    /// Gets the value of variable and pushes onto the stack
+    /// Gets the value of variable and pushes onto the stack. Slot of the variable is cached for the instruction, so next time
+    /// it's executed, e.g. in a loop, the name isn't looked up again.
     pub(super) fn op_get_global(&mut self) -> Result<(), VMError> {
-        // Read the variable name from bytecode and convert it to literal string
-        let name = self.current_frame().read_constant().as_string();
-        // Get the global variable from `HashMap`
-        let value = self.globals.get(&name).cloned().ok_or_else(|| {
-            // Variable doesn't exist. Return an error.
-            self.construct_runtime_error(format_args!("Undefined variable '{name}'"))
-        })?;
+        let index = self.read_byte()?;
+        // Operand has just been read, so there's a frame. It's borrowed immutably, so the name can be compared with cached slots.
+        let frame = &self.frames[self.frames.len() - 1];
+        let function = frame.function.as_function_ref();
+        // Offset right after the operand identifies the instruction in its function
+        let key = (ptr::from_ref(function) as usize, frame.ip_offset);
+        // Name is read by reference, cloning it would cost as much as looking it up
+        let Some(name) = function
+            .chunk
+            .constants
+            .get(index as usize)
+            .filter(|constant| constant.is_string())
+            .map(Value::as_string_ref)
+        else {
+            return Err(self.construct_runtime_error(format_args!(
+                "Expected name of global variable in constant {index}"
+            )));
+        };
+
+        // Address of a freed function can be reused by another one, so name of the cached slot is checked before trusting it
+        let cached = self
+            .global_cache
+            .get(&key)
+            .copied()
+            .filter(|&slot| self.global_names[slot] == name);
+        let slot = if let Some(slot) = cached {
+            slot
+        } else {
+            // Get the slot of global variable from `HashMap`
+            let Some(&slot) = self.globals.get(name) else {
+                // Variable doesn't exist. Return an error.
+                let name = name.to_owned();
+                return Err(
+                    self.construct_runtime_error(format_args!("Undefined variable '{name}'"))
+                );
+            };
+            self.global_cache.insert(key, slot);
+            slot
+        };
         // Variable exists, push value on the stack for later use.
-        self.push(value);
+        self.push(self.global_values[slot].clone());
         Ok(())
     }
 
     /// Sets value to already declared global variable
     pub(super) fn op_set_global(&mut self) -> Result<(), VMError> {
         // Read the variable name from bytecode and convert it to literal string
-        let name = self.current_frame().read_constant().as_string();
+        let name = self.read_string_constant()?;
         // Check for underflow. If `stack_top` is less than zero after subtraction, return error
         let value_index =
             self.stack.len().checked_sub(1).ok_or_else(|| {
@@ -52,12 +137,12 @@ impl VM {
         // Clone value from the stack. We just want to store it in HashMap, so no need to pop or replace value.
         let value = self.stack[value_index].clone();
         // Check whether variable is defined or not
-        if !self.globals.contains_key(&name) {
+        let Some(&slot) = self.globals.get(&name) else {
             // Variable has not been defined, return error.
             return Err(self.construct_runtime_error(format_args!("Undefined variable '{name}'")));
-        }
+        };
         // Variable has been defined. Update it's value
-        self.globals.insert(name, value);
+        self.global_values[slot] = value;
 
         Ok(())
     }